@@ -0,0 +1,86 @@
+use std::{env, path::PathBuf};
+
+use crate::simbar::DrawSize;
+
+const USAGE: &str = "\
+Usage: smithay-bar-example [OPTIONS]
+
+Options:
+  -c, --config <PATH>       Read the configuration from PATH instead of
+                            $XDG_CONFIG_HOME/simbar/config.toml
+      --render-png <PATH>   Render the bar once to a PNG file and exit,
+                            without connecting to a compositor
+      --size <WIDTH>x<HEIGHT>
+                            Size of the --render-png image, defaults to the
+                            configured bar size
+      --supervise           Keep running when the compositor goes away and
+                            reconnect once it is back
+  -h, --help                Print this help and exit";
+
+/// Command line options of the bar.
+#[derive(Default)]
+pub struct CliArgs {
+    /// Explicit configuration file, overriding the XDG location.
+    pub config: Option<PathBuf>,
+    /// Where to write a headless render of the bar, instead of running it.
+    pub render_png: Option<PathBuf>,
+    /// Size of the headless render.
+    pub size: Option<DrawSize>,
+    /// Reconnect instead of exiting when the Wayland connection is lost.
+    pub supervise: bool,
+}
+
+impl CliArgs {
+    /// Parses the process arguments.
+    ///
+    /// Prints the usage and exits on `--help`; returns a message on invalid input.
+    pub fn parse() -> Result<Self, String> {
+        let mut args = CliArgs::default();
+        let mut argv = env::args_os().skip(1);
+
+        while let Some(arg) = argv.next() {
+            match arg.to_str() {
+                Some("-c" | "--config") => {
+                    let path = argv.next().ok_or("--config requires a path")?;
+                    args.config = Some(path.into());
+                }
+                Some("--render-png") => {
+                    let path = argv.next().ok_or("--render-png requires a path")?;
+                    args.render_png = Some(path.into());
+                }
+                Some("--size") => {
+                    let size = argv.next().ok_or("--size requires a size")?;
+                    args.size = Some(parse_size(&size.to_string_lossy())?);
+                }
+                Some("--supervise") => args.supervise = true,
+                Some("-h" | "--help") => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => {
+                    return Err(format!(
+                        "unexpected argument '{}'\n\n{USAGE}",
+                        arg.to_string_lossy()
+                    ));
+                }
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Parses a `<WIDTH>x<HEIGHT>` size, e.g. `1920x40`.
+fn parse_size(value: &str) -> Result<DrawSize, String> {
+    let invalid = || format!("invalid size '{value}', expected <WIDTH>x<HEIGHT>");
+
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width: u32 = width.parse().map_err(|_| invalid())?;
+    let height: u32 = height.parse().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+
+    Ok((width, height).into())
+}
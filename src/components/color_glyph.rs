@@ -0,0 +1,857 @@
+use std::f32::consts::PI;
+
+use ab_glyph_rasterizer::{Point, Rasterizer, point};
+use rusttype::PositionedGlyph;
+use rustybuzz::ttf_parser::{
+    GlyphId, OutlineBuilder, RasterImageFormat, RgbaColor, Transform,
+    colr::{ClipBox, CompositeMode, GradientExtend, Paint, Painter},
+};
+
+use crate::fonts::FontFace;
+
+use super::{
+    colors::{ArgbColor, RgbColor},
+    glyph_cache::{GlyphBitmap, Pixels},
+};
+
+/// A color with red, green and blue premultiplied by alpha, each channel from 0 to 1.
+type Rgba = [f32; 4];
+
+/// Draws `glyph` in the colors `face` gives it, from its `COLR` table or from a color bitmap
+/// of its `CBDT` or `sbix` table, with `foreground` for the parts drawn in the text color.
+///
+/// Returns `None` when the font has no colors for the glyph, which is then drawn from its
+/// outline like any other.
+pub fn draw(
+    face: &'static FontFace,
+    glyph: &PositionedGlyph<'static>,
+    foreground: RgbColor,
+) -> Option<GlyphBitmap> {
+    let id = GlyphId(glyph.id().0);
+
+    let canvas = match face.shaper.is_color_glyph(id) {
+        true => paint(face, id, glyph, foreground)?,
+        false => bitmap(face, id, glyph)?,
+    };
+
+    canvas.into_bitmap()
+}
+
+/// Premultiplied pixels, placed relative to the pixel the glyph origin falls in.
+struct Canvas {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgba>,
+}
+
+impl Canvas {
+    fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        let (width, height) = (
+            (right - left).max(0) as usize,
+            (bottom - top).max(0) as usize,
+        );
+
+        Self {
+            left,
+            top,
+            width,
+            height,
+            pixels: vec![[0.0; 4]; width * height],
+        }
+    }
+
+    /// Crops the transparent border off, or returns `None` when nothing was drawn.
+    fn into_bitmap(self) -> Option<GlyphBitmap> {
+        let drawn = |x: usize, y: usize| self.pixels[y * self.width + x][3] > 0.0;
+
+        let rows: Vec<_> = (0..self.height)
+            .filter(|&y| (0..self.width).any(|x| drawn(x, y)))
+            .collect();
+        let columns: Vec<_> = (0..self.width)
+            .filter(|&x| (0..self.height).any(|y| drawn(x, y)))
+            .collect();
+        let (&top, &bottom) = (rows.first()?, rows.last()?);
+        let (&left, &right) = (columns.first()?, columns.last()?);
+
+        let pixels = (top..=bottom)
+            .flat_map(|y| (left..=right).map(move |x| (x, y)))
+            .map(|(x, y)| unpremultiply(self.pixels[y * self.width + x]))
+            .collect();
+
+        Some(GlyphBitmap {
+            width: right - left + 1,
+            pixels: Pixels::Color(pixels),
+            left: self.left + left as i32,
+            top: self.top + top as i32,
+        })
+    }
+}
+
+fn unpremultiply([red, green, blue, alpha]: Rgba) -> ArgbColor {
+    if alpha <= 0.0 {
+        return ArgbColor::new(0.0, 0x00, 0x00, 0x00);
+    }
+
+    let channel = |value: f32| (value / alpha * 255.0).round().clamp(0.0, 255.0) as u8;
+    ArgbColor::new(alpha, channel(red), channel(green), channel(blue))
+}
+
+fn premultiply([red, green, blue, alpha]: [u8; 4]) -> Rgba {
+    let alpha = f32::from(alpha) / 255.0;
+    let channel = |value: u8| f32::from(value) / 255.0 * alpha;
+    [channel(red), channel(green), channel(blue), alpha]
+}
+
+/// Draws the color bitmap of the strike closest to the glyph size, scaled to it.
+fn bitmap(face: &FontFace, id: GlyphId, glyph: &PositionedGlyph<'static>) -> Option<Canvas> {
+    // The size of an em in pixels
+    let ppem = face.pixels_per_unit(glyph.scale()).1 * f32::from(face.font.units_per_em());
+    let image = face.shaper.glyph_raster_image(id, ppem.round() as u16)?;
+
+    let (width, height, source) = match image.format {
+        RasterImageFormat::PNG => decode_png(image.data)?,
+        RasterImageFormat::BitmapPremulBgra32 => {
+            let (width, height) = (usize::from(image.width), usize::from(image.height));
+            let channel = |value: u8| f32::from(value) / 255.0;
+            let pixels: Vec<_> = image
+                .data
+                .chunks_exact(4)
+                .map(|bgra| {
+                    [
+                        channel(bgra[2]),
+                        channel(bgra[1]),
+                        channel(bgra[0]),
+                        channel(bgra[3]),
+                    ]
+                })
+                .collect();
+
+            if pixels.len() < width * height {
+                return None;
+            }
+            (width, height, pixels)
+        }
+        // Monochrome and grayscale bitmaps are drawn from the outline instead
+        _ => return None,
+    };
+
+    let factor = ppem / f32::from(image.pixels_per_em.max(1));
+    let origin = glyph.position();
+
+    // Image edges in pixels; `x` and `y` are its bottom left corner above the baseline
+    let x0 = origin.x + f32::from(image.x) * factor;
+    let y0 = origin.y - (f32::from(image.y) + height as f32) * factor;
+    let x1 = x0 + width as f32 * factor;
+    let y1 = y0 + height as f32 * factor;
+
+    let mut canvas = Canvas::new(
+        x0.floor() as i32,
+        y0.floor() as i32,
+        x1.ceil() as i32,
+        y1.ceil() as i32,
+    );
+
+    // Every pixel averages the part of the image it covers
+    let columns = coverage_weights(x0 - canvas.left as f32, factor, width, canvas.width);
+    let rows = coverage_weights(y0 - canvas.top as f32, factor, height, canvas.height);
+
+    for (y, row) in rows.iter().enumerate() {
+        for (x, column) in columns.iter().enumerate() {
+            let mut sum = [0.0; 4];
+
+            for &(source_y, weight_y) in row {
+                for &(source_x, weight_x) in column {
+                    let pixel = source[source_y * width + source_x];
+                    for (sum, value) in sum.iter_mut().zip(pixel) {
+                        *sum += value * weight_x * weight_y;
+                    }
+                }
+            }
+
+            canvas.pixels[y * canvas.width + x] = sum;
+        }
+    }
+
+    Some(canvas)
+}
+
+/// For each of `count` pixels, the pixels of a `length` long source row or column it covers
+/// and the share of the pixel each takes, once the source is scaled by `factor` and moved
+/// `start` pixels along.
+fn coverage_weights(
+    start: f32,
+    factor: f32,
+    length: usize,
+    count: usize,
+) -> Vec<Vec<(usize, f32)>> {
+    (0..count)
+        .map(|pixel| {
+            // The pixel edges in source pixels
+            let from = (pixel as f32 - start) / factor;
+            let to = (pixel as f32 + 1.0 - start) / factor;
+
+            (from.floor().max(0.0) as usize..(to.ceil().max(0.0) as usize).min(length))
+                .map(|source| {
+                    let overlap = to.min(source as f32 + 1.0) - from.max(source as f32);
+                    (source, overlap.max(0.0) * factor)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Decodes a PNG image into its width, height and premultiplied pixels.
+fn decode_png(data: &[u8]) -> Option<(usize, usize, Vec<Rgba>)> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).ok()?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let buffer = &buffer[..info.buffer_size()];
+
+    let pixels: Vec<_> = match info.color_type {
+        png::ColorType::Rgba => buffer
+            .chunks_exact(4)
+            .map(|p| premultiply([p[0], p[1], p[2], p[3]]))
+            .collect(),
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .map(|p| premultiply([p[0], p[1], p[2], 0xFF]))
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .map(|p| premultiply([p[0], p[0], p[0], p[1]]))
+            .collect(),
+        png::ColorType::Grayscale => buffer
+            .iter()
+            .map(|&p| premultiply([p, p, p, 0xFF]))
+            .collect(),
+        // Palettes are expanded by the decoder
+        png::ColorType::Indexed => return None,
+    };
+
+    (pixels.len() >= width * height).then_some((width, height, pixels))
+}
+
+/// Paints the layers of a `COLR` glyph.
+fn paint(
+    face: &'static FontFace,
+    id: GlyphId,
+    glyph: &PositionedGlyph<'static>,
+    foreground: RgbColor,
+) -> Option<Canvas> {
+    let (scale_x, scale_y) = face.pixels_per_unit(glyph.scale());
+    let v_metrics = face.font.v_metrics_unscaled();
+    let origin = glyph.position();
+
+    // Color glyphs are drawn within their advance and the line, but may spill out of them
+    let margin = f32::from(face.font.units_per_em()) / 4.0;
+    let bbox = face.shaper.glyph_bounding_box(id);
+    let advance = f32::from(face.shaper.glyph_hor_advance(id).unwrap_or(0));
+    let x_min = bbox.map_or(0.0, |bbox| f32::from(bbox.x_min)).min(0.0) - margin;
+    let x_max = bbox.map_or(0.0, |bbox| f32::from(bbox.x_max)).max(advance) + margin;
+    let y_min = bbox
+        .map_or(0.0, |bbox| f32::from(bbox.y_min))
+        .min(v_metrics.descent)
+        - margin;
+    let y_max = bbox
+        .map_or(0.0, |bbox| f32::from(bbox.y_max))
+        .max(v_metrics.ascent)
+        + margin;
+
+    let canvas = Canvas::new(
+        (origin.x + x_min * scale_x).floor() as i32,
+        (origin.y - y_max * scale_y).floor() as i32,
+        (origin.x + x_max * scale_x).ceil() as i32,
+        (origin.y - y_min * scale_y).ceil() as i32,
+    );
+
+    let mut painter = ColrPainter {
+        face,
+        width: canvas.width,
+        height: canvas.height,
+        // Font units, upwards, to canvas pixels, downwards
+        transforms: vec![Transform::new(
+            scale_x,
+            0.0,
+            0.0,
+            -scale_y,
+            origin.x - canvas.left as f32,
+            origin.y - canvas.top as f32,
+        )],
+        outline: None,
+        clips: Vec::new(),
+        layers: vec![(CompositeMode::SourceOver, canvas.pixels)],
+    };
+
+    let foreground = RgbaColor::new(foreground.red, foreground.green, foreground.blue, 0xFF);
+    face.shaper
+        .paint_color_glyph(id, 0, foreground, &mut painter)?;
+
+    let (_, pixels) = painter.layers.swap_remove(0);
+    Some(Canvas { pixels, ..canvas })
+}
+
+/// Draws the paint operations of a `COLR` glyph on premultiplied layers.
+struct ColrPainter {
+    face: &'static FontFace,
+    width: usize,
+    height: usize,
+    /// Font units to canvas pixels, followed by the combination of every transform pushed.
+    transforms: Vec<Transform>,
+    /// Coverage of the last glyph outlined, until it is painted or clipped to.
+    outline: Option<Vec<f32>>,
+    /// Coverage of the intersection of the clips pushed so far, the innermost last.
+    clips: Vec<Vec<f32>>,
+    /// Layers being drawn, each composited onto the one below with its mode when popped.
+    layers: Vec<(CompositeMode, Vec<Rgba>)>,
+}
+
+impl ColrPainter {
+    fn transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+
+    /// Rasterizes the path `build` draws in font units with the current transform.
+    fn coverage(&self, build: impl FnOnce(&mut PathRasterizer)) -> Vec<f32> {
+        let mut path = PathRasterizer {
+            rasterizer: Rasterizer::new(self.width, self.height),
+            transform: self.transform(),
+            start: Point::default(),
+            last: Point::default(),
+        };
+
+        build(&mut path);
+        path.close();
+
+        let mut coverage = vec![0.0; self.width * self.height];
+        path.rasterizer
+            .for_each_pixel(|index, alpha| coverage[index] = alpha.min(1.0));
+        coverage
+    }
+
+    fn push_clip_mask(&mut self, mut mask: Vec<f32>) {
+        if let Some(clip) = self.clips.last() {
+            for (mask, clip) in mask.iter_mut().zip(clip) {
+                *mask *= clip;
+            }
+        }
+
+        self.clips.push(mask);
+    }
+}
+
+impl Painter<'static> for ColrPainter {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        let face = self.face;
+        self.outline = Some(self.coverage(|path| {
+            face.shaper.outline_glyph(glyph_id, path);
+        }));
+    }
+
+    /// Fills the last glyph outlined, or the whole clip when it was already clipped to.
+    fn paint(&mut self, paint: Paint<'static>) {
+        let outline = self.outline.take();
+        let (Some(fill), Some(inverse)) = (Fill::new(paint, self.face), invert(self.transform()))
+        else {
+            return;
+        };
+        let Some((_, layer)) = self.layers.last_mut() else {
+            return;
+        };
+
+        for (index, pixel) in layer.iter_mut().enumerate() {
+            let coverage = outline.as_ref().map_or(1.0, |outline| outline[index])
+                * self.clips.last().map_or(1.0, |clip| clip[index]);
+
+            if coverage <= 0.0 {
+                continue;
+            }
+
+            let x = (index % self.width) as f32 + 0.5;
+            let y = (index / self.width) as f32 + 0.5;
+
+            if let Some(color) = fill.color_at(apply(inverse, x, y)) {
+                *pixel = composite(
+                    CompositeMode::SourceOver,
+                    color.map(|channel| channel * coverage),
+                    *pixel,
+                );
+            }
+        }
+    }
+
+    fn push_clip(&mut self) {
+        let mask = self
+            .outline
+            .take()
+            .unwrap_or_else(|| vec![0.0; self.width * self.height]);
+        self.push_clip_mask(mask);
+    }
+
+    fn push_clip_box(&mut self, clip_box: ClipBox) {
+        let mask = self.coverage(|path| {
+            path.move_to(clip_box.x_min, clip_box.y_min);
+            path.line_to(clip_box.x_max, clip_box.y_min);
+            path.line_to(clip_box.x_max, clip_box.y_max);
+            path.line_to(clip_box.x_min, clip_box.y_max);
+        });
+        self.push_clip_mask(mask);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn push_layer(&mut self, mode: CompositeMode) {
+        self.layers
+            .push((mode, vec![[0.0; 4]; self.width * self.height]));
+    }
+
+    fn pop_layer(&mut self) {
+        if self.layers.len() < 2 {
+            return;
+        }
+
+        let (mode, source) = self.layers.pop().expect("Layer stack is empty");
+        let (_, backdrop) = self.layers.last_mut().expect("Layer stack is empty");
+
+        for (backdrop, source) in backdrop.iter_mut().zip(source) {
+            *backdrop = composite(mode, source, *backdrop);
+        }
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.transforms
+            .push(Transform::combine(self.transform(), transform));
+    }
+
+    fn pop_transform(&mut self) {
+        // The first transform is the one to canvas pixels
+        if self.transforms.len() > 1 {
+            self.transforms.pop();
+        }
+    }
+}
+
+/// Rasterizes a glyph outline or another path, mapped to pixels by `transform`.
+struct PathRasterizer {
+    rasterizer: Rasterizer,
+    transform: Transform,
+    start: Point,
+    last: Point,
+}
+
+impl PathRasterizer {
+    fn map(&self, x: f32, y: f32) -> Point {
+        let (x, y) = apply(self.transform, x, y);
+        point(x, y)
+    }
+}
+
+impl OutlineBuilder for PathRasterizer {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.close();
+        self.start = self.map(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let to = self.map(x, y);
+        self.rasterizer.draw_line(self.last, to);
+        self.last = to;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let to = self.map(x, y);
+        self.rasterizer.draw_quad(self.last, self.map(x1, y1), to);
+        self.last = to;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let to = self.map(x, y);
+        self.rasterizer
+            .draw_cubic(self.last, self.map(x1, y1), self.map(x2, y2), to);
+        self.last = to;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
+
+fn apply(transform: Transform, x: f32, y: f32) -> (f32, f32) {
+    (
+        transform.a * x + transform.c * y + transform.e,
+        transform.b * x + transform.d * y + transform.f,
+    )
+}
+
+fn invert(transform: Transform) -> Option<Transform> {
+    let Transform { a, b, c, d, e, f } = transform;
+    let determinant = a * d - b * c;
+
+    (determinant.abs() > f32::EPSILON).then(|| {
+        Transform::new(
+            d / determinant,
+            -b / determinant,
+            -c / determinant,
+            a / determinant,
+            (c * f - d * e) / determinant,
+            (b * e - a * f) / determinant,
+        )
+    })
+}
+
+/// A paint ready to be sampled anywhere in its own coordinates, the font units it was
+/// defined in.
+enum Fill {
+    Solid(Rgba),
+    Linear {
+        start: (f32, f32),
+        /// From the start to where the color line ends, perpendicular to the lines of
+        /// constant color.
+        vector: (f32, f32),
+        colors: ColorLine,
+    },
+    Radial {
+        center0: (f32, f32),
+        radius0: f32,
+        center1: (f32, f32),
+        radius1: f32,
+        colors: ColorLine,
+    },
+    Sweep {
+        center: (f32, f32),
+        /// Angles in half turns, counterclockwise from the positive x axis.
+        start_angle: f32,
+        end_angle: f32,
+        colors: ColorLine,
+    },
+}
+
+impl Fill {
+    /// Returns `None` for gradients without color stops or with nothing to draw.
+    fn new(paint: Paint<'static>, face: &'static FontFace) -> Option<Self> {
+        let coords = face.shaper.variation_coordinates();
+
+        Some(match paint {
+            Paint::Solid(color) => Fill::Solid(premultiply([
+                color.red,
+                color.green,
+                color.blue,
+                color.alpha,
+            ])),
+            Paint::LinearGradient(gradient) => {
+                let colors = ColorLine::new(
+                    gradient
+                        .stops(0, coords)
+                        .map(|stop| (stop.stop_offset, stop.color)),
+                    gradient.extend,
+                )?;
+
+                // The color line runs from p0 to p1, turned to be perpendicular to p0-p2
+                let (x0, y0) = (gradient.x0, gradient.y0);
+                let (dx, dy) = (gradient.x1 - x0, gradient.y1 - y0);
+                let (nx, ny) = (gradient.y2 - y0, x0 - gradient.x2);
+                let norm = nx * nx + ny * ny;
+                let vector = match norm > 0.0 {
+                    true => {
+                        let projection = (dx * nx + dy * ny) / norm;
+                        (nx * projection, ny * projection)
+                    }
+                    false => (dx, dy),
+                };
+
+                if vector == (0.0, 0.0) {
+                    return None;
+                }
+
+                Fill::Linear {
+                    start: (x0, y0),
+                    vector,
+                    colors,
+                }
+            }
+            Paint::RadialGradient(gradient) => Fill::Radial {
+                colors: ColorLine::new(
+                    gradient
+                        .stops(0, coords)
+                        .map(|stop| (stop.stop_offset, stop.color)),
+                    gradient.extend,
+                )?,
+                center0: (gradient.x0, gradient.y0),
+                radius0: gradient.r0,
+                center1: (gradient.x1, gradient.y1),
+                radius1: gradient.r1,
+            },
+            Paint::SweepGradient(gradient) => Fill::Sweep {
+                colors: ColorLine::new(
+                    gradient
+                        .stops(0, coords)
+                        .map(|stop| (stop.stop_offset, stop.color)),
+                    gradient.extend,
+                )?,
+                center: (gradient.center_x, gradient.center_y),
+                start_angle: gradient.start_angle,
+                end_angle: gradient.end_angle,
+            },
+        })
+    }
+
+    /// The color at `(x, y)`, or `None` where a gradient is not defined.
+    fn color_at(&self, (x, y): (f32, f32)) -> Option<Rgba> {
+        match self {
+            Fill::Solid(color) => Some(*color),
+            Fill::Linear {
+                start,
+                vector,
+                colors,
+            } => {
+                let t = ((x - start.0) * vector.0 + (y - start.1) * vector.1)
+                    / (vector.0 * vector.0 + vector.1 * vector.1);
+                Some(colors.color_at(t))
+            }
+            Fill::Radial {
+                center0,
+                radius0,
+                center1,
+                radius1,
+                colors,
+            } => {
+                // The largest t for which (x, y) is on the circle interpolated between both,
+                // with a radius that is not negative
+                let (cx, cy) = (center1.0 - center0.0, center1.1 - center0.1);
+                let (px, py) = (x - center0.0, y - center0.1);
+                let dr = radius1 - radius0;
+
+                let a = cx * cx + cy * cy - dr * dr;
+                let b = px * cx + py * cy + radius0 * dr;
+                let c = px * px + py * py - radius0 * radius0;
+
+                let candidates = match a.abs() < f32::EPSILON {
+                    true if b == 0.0 => return None,
+                    true => [c / (2.0 * b), f32::NAN],
+                    false => {
+                        let discriminant = b * b - a * c;
+                        if discriminant < 0.0 {
+                            return None;
+                        }
+
+                        let root = discriminant.sqrt();
+                        let (t1, t2) = ((b + root) / a, (b - root) / a);
+                        [t1.max(t2), t1.min(t2)]
+                    }
+                };
+
+                candidates
+                    .into_iter()
+                    .find(|t| radius0 + t * dr >= 0.0)
+                    .map(|t| colors.color_at(t))
+            }
+            Fill::Sweep {
+                center,
+                start_angle,
+                end_angle,
+                colors,
+            } => {
+                if start_angle == end_angle {
+                    return None;
+                }
+
+                let angle = (y - center.1).atan2(x - center.0) / PI;
+                let t = (angle.rem_euclid(2.0) - start_angle) / (end_angle - start_angle);
+                Some(colors.color_at(t))
+            }
+        }
+    }
+}
+
+/// The color stops of a gradient, sorted by offset.
+struct ColorLine {
+    stops: Vec<(f32, Rgba)>,
+    extend: GradientExtend,
+}
+
+impl ColorLine {
+    fn new(stops: impl Iterator<Item = (f32, RgbaColor)>, extend: GradientExtend) -> Option<Self> {
+        let mut stops: Vec<_> = stops
+            .map(|(offset, color)| {
+                (
+                    offset,
+                    premultiply([color.red, color.green, color.blue, color.alpha]),
+                )
+            })
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        (!stops.is_empty()).then_some(Self { stops, extend })
+    }
+
+    /// The color at `t` along the line, interpolated between premultiplied stops.
+    fn color_at(&self, t: f32) -> Rgba {
+        let first = self.stops[0].0;
+        let span = self.stops[self.stops.len() - 1].0 - first;
+
+        // Repetitions cover the range of the stops
+        let t = match self.extend {
+            GradientExtend::Repeat if span > 0.0 => first + (t - first).rem_euclid(span),
+            GradientExtend::Reflect if span > 0.0 => {
+                let t = (t - first).rem_euclid(2.0 * span);
+                first + if t > span { 2.0 * span - t } else { t }
+            }
+            _ => t,
+        };
+
+        // Before the first stop and after the last one, their colors extend
+        match self.stops.iter().position(|(offset, _)| *offset > t) {
+            Some(0) => self.stops[0].1,
+            None => self.stops[self.stops.len() - 1].1,
+            Some(next) => {
+                let (offset0, color0) = self.stops[next - 1];
+                let (offset1, color1) = self.stops[next];
+                let f = (t - offset0) / (offset1 - offset0);
+                std::array::from_fn(|i| color0[i] + (color1[i] - color0[i]) * f)
+            }
+        }
+    }
+}
+
+/// Composites `source` onto `backdrop`, both premultiplied.
+fn composite(mode: CompositeMode, source: Rgba, backdrop: Rgba) -> Rgba {
+    let (source_alpha, backdrop_alpha) = (source[3], backdrop[3]);
+
+    // Porter-Duff operators weigh the source and the backdrop
+    let weigh = |source_weight: f32, backdrop_weight: f32| -> Rgba {
+        std::array::from_fn(|i| source[i] * source_weight + backdrop[i] * backdrop_weight)
+    };
+
+    match mode {
+        CompositeMode::Clear => [0.0; 4],
+        CompositeMode::Source => source,
+        CompositeMode::Destination => backdrop,
+        CompositeMode::SourceOver => weigh(1.0, 1.0 - source_alpha),
+        CompositeMode::DestinationOver => weigh(1.0 - backdrop_alpha, 1.0),
+        CompositeMode::SourceIn => weigh(backdrop_alpha, 0.0),
+        CompositeMode::DestinationIn => weigh(0.0, source_alpha),
+        CompositeMode::SourceOut => weigh(1.0 - backdrop_alpha, 0.0),
+        CompositeMode::DestinationOut => weigh(0.0, 1.0 - source_alpha),
+        CompositeMode::SourceAtop => weigh(backdrop_alpha, 1.0 - source_alpha),
+        CompositeMode::DestinationAtop => weigh(1.0 - backdrop_alpha, source_alpha),
+        CompositeMode::Xor => weigh(1.0 - backdrop_alpha, 1.0 - source_alpha),
+        CompositeMode::Plus => std::array::from_fn(|i| (source[i] + backdrop[i]).min(1.0)),
+        _ => blend(mode, source, backdrop),
+    }
+}
+
+/// Composites with one of the blend modes of the W3C Compositing and Blending
+/// specification, which mix the colors where the source and backdrop overlap.
+fn blend(mode: CompositeMode, source: Rgba, backdrop: Rgba) -> Rgba {
+    let (source_alpha, backdrop_alpha) = (source[3], backdrop[3]);
+    let straight = |color: Rgba| match color[3] > 0.0 {
+        true => [
+            color[0] / color[3],
+            color[1] / color[3],
+            color[2] / color[3],
+        ],
+        false => [0.0; 3],
+    };
+    let (cs, cb) = (straight(source), straight(backdrop));
+
+    let mixed = match mode {
+        CompositeMode::Hue => set_luminosity(set_saturation(cs, saturation(cb)), luminosity(cb)),
+        CompositeMode::Saturation => {
+            set_luminosity(set_saturation(cb, saturation(cs)), luminosity(cb))
+        }
+        CompositeMode::Color => set_luminosity(cs, luminosity(cb)),
+        CompositeMode::Luminosity => set_luminosity(cb, luminosity(cs)),
+        _ => std::array::from_fn(|i| blend_channel(mode, cs[i], cb[i])),
+    };
+
+    let mut result = [
+        0.0,
+        0.0,
+        0.0,
+        source_alpha + backdrop_alpha - source_alpha * backdrop_alpha,
+    ];
+    for i in 0..3 {
+        result[i] = source[i] * (1.0 - backdrop_alpha)
+            + backdrop[i] * (1.0 - source_alpha)
+            + source_alpha * backdrop_alpha * mixed[i];
+    }
+    result
+}
+
+/// Blends one channel of the source `s` with the backdrop `b`, both not premultiplied.
+fn blend_channel(mode: CompositeMode, s: f32, b: f32) -> f32 {
+    match mode {
+        CompositeMode::Multiply => s * b,
+        CompositeMode::Screen => s + b - s * b,
+        CompositeMode::Overlay => blend_channel(CompositeMode::HardLight, b, s),
+        CompositeMode::Darken => s.min(b),
+        CompositeMode::Lighten => s.max(b),
+        CompositeMode::ColorDodge if b <= 0.0 => 0.0,
+        CompositeMode::ColorDodge if s >= 1.0 => 1.0,
+        CompositeMode::ColorDodge => (b / (1.0 - s)).min(1.0),
+        CompositeMode::ColorBurn if b >= 1.0 => 1.0,
+        CompositeMode::ColorBurn if s <= 0.0 => 0.0,
+        CompositeMode::ColorBurn => 1.0 - ((1.0 - b) / s).min(1.0),
+        CompositeMode::HardLight if s <= 0.5 => b * 2.0 * s,
+        CompositeMode::HardLight => blend_channel(CompositeMode::Screen, 2.0 * s - 1.0, b),
+        CompositeMode::SoftLight if s <= 0.5 => b - (1.0 - 2.0 * s) * b * (1.0 - b),
+        CompositeMode::SoftLight => {
+            let d = match b <= 0.25 {
+                true => ((16.0 * b - 12.0) * b + 4.0) * b,
+                false => b.sqrt(),
+            };
+            b + (2.0 * s - 1.0) * (d - b)
+        }
+        CompositeMode::Difference => (s - b).abs(),
+        CompositeMode::Exclusion => s + b - 2.0 * s * b,
+        _ => s,
+    }
+}
+
+fn luminosity([r, g, b]: [f32; 3]) -> f32 {
+    0.3 * r + 0.59 * g + 0.11 * b
+}
+
+fn saturation(color: [f32; 3]) -> f32 {
+    color.iter().copied().fold(f32::MIN, f32::max) - color.iter().copied().fold(f32::MAX, f32::min)
+}
+
+fn set_luminosity(color: [f32; 3], target: f32) -> [f32; 3] {
+    let delta = target - luminosity(color);
+    let color = color.map(|channel| channel + delta);
+
+    // Bring the channels back between 0 and 1, keeping the luminosity
+    let l = luminosity(color);
+    let min = color.iter().copied().fold(f32::MAX, f32::min);
+    let max = color.iter().copied().fold(f32::MIN, f32::max);
+
+    let color = match min < 0.0 && l > min {
+        true => color.map(|channel| l + (channel - l) * l / (l - min)),
+        false => color,
+    };
+    match max > 1.0 && max > l {
+        true => color.map(|channel| l + (channel - l) * (1.0 - l) / (max - l)),
+        false => color,
+    }
+}
+
+fn set_saturation(color: [f32; 3], target: f32) -> [f32; 3] {
+    let min = color.iter().copied().fold(f32::MAX, f32::min);
+    let max = color.iter().copied().fold(f32::MIN, f32::max);
+
+    color.map(|channel| match max > min {
+        true => (channel - min) * target / (max - min),
+        false => 0.0,
+    })
+}
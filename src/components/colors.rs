@@ -1,4 +1,8 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, de::Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RgbColor {
     pub red: u8,
     pub green: u8,
@@ -120,3 +124,58 @@ impl From<RgbColor> for ArgbColor {
         }
     }
 }
+
+/// Parses `#RRGGBB` into an opaque color and `#AARRGGBB` into a translucent one.
+impl FromStr for ArgbColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let hex = value
+            .strip_prefix('#')
+            .ok_or_else(|| format!("color `{value}` must start with `#`"))?;
+
+        let channels = match hex.len() {
+            6 | 8 if hex.is_ascii() => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| format!("color `{value}` is not valid hexadecimal"))?,
+            _ => return Err(format!("color `{value}` must be `#RRGGBB` or `#AARRGGBB`")),
+        };
+
+        Ok(match channels[..] {
+            [red, green, blue] => Self::new(1.0, red, green, blue),
+            [alpha, red, green, blue] => Self::new(alpha as f32 / 255.0, red, green, blue),
+            _ => unreachable!(),
+        })
+    }
+}
+
+impl FromStr for RgbColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.parse::<ArgbColor>()? {
+            color if color.alpha < 1.0 => {
+                Err(format!("color `{value}` must be `#RRGGBB` without alpha"))
+            }
+            color => Ok(Self::new(color.red, color.green, color.blue)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ArgbColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for RgbColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
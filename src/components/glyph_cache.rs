@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use rusttype::{GlyphId, PositionedGlyph, point};
+
+use crate::fonts::FontFace;
+
+use super::{
+    color_glyph,
+    colors::{ArgbColor, RgbColor},
+};
+
+/// Subpixel positions per pixel a glyph is rasterized at; positions in between are rounded.
+const SUBPIXEL_STEPS: i32 = 8;
+
+/// Bound on the number of cached glyphs, the cache is emptied when it is reached.
+const MAX_GLYPHS: usize = 4096;
+
+/// A rasterized glyph: the pixels of its bounding box, row by row.
+pub struct GlyphBitmap {
+    pub width: usize,
+    pub pixels: Pixels,
+    /// Offset of the bounding box from the pixel the glyph origin falls in.
+    pub(super) left: i32,
+    pub(super) top: i32,
+}
+
+pub enum Pixels {
+    /// Coverage of the glyph outline, drawn in the text color.
+    Coverage(Vec<f32>),
+    /// Colors of the glyph's own, from the color tables of the font.
+    Color(Vec<ArgbColor>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    /// Address of the font, which lives for the whole program.
+    font: usize,
+    glyph: GlyphId,
+    /// Bits of the `f32` scale, in pixels per em.
+    size: (u32, u32),
+    /// Subpixel offset of the origin, in [`SUBPIXEL_STEPS`].
+    subpixel: (u8, u8),
+    /// Text color, for `COLR` glyphs which may paint parts of themselves with it.
+    foreground: Option<RgbColor>,
+}
+
+/// Glyphs without an outline are cached as `None`.
+static CACHE: LazyLock<Mutex<HashMap<GlyphKey, Option<Arc<GlyphBitmap>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Rasterizes `glyph` of `face`, or takes it from the cache shared by every label.
+/// `foreground` is the color of the text.
+///
+/// Returns the canvas position of the top left corner of the bitmap, or `None` for glyphs
+/// with nothing to draw such as spaces.
+pub fn rasterize(
+    face: &'static FontFace,
+    glyph: &PositionedGlyph<'static>,
+    foreground: RgbColor,
+) -> Option<(i32, i32, Arc<GlyphBitmap>)> {
+    // Split the position, rounded to the nearest subpixel step, into pixel and step
+    let split = |position: f32| {
+        let steps = (position * SUBPIXEL_STEPS as f32).round() as i32;
+        (
+            steps.div_euclid(SUBPIXEL_STEPS),
+            steps.rem_euclid(SUBPIXEL_STEPS) as u8,
+        )
+    };
+    let position = glyph.position();
+    let ((x, step_x), (y, step_y)) = (split(position.x), split(position.y));
+    let scale = glyph.scale();
+    let id = glyph.id();
+
+    let key = GlyphKey {
+        font: std::ptr::from_ref(face) as usize,
+        glyph: id,
+        size: (scale.x.to_bits(), scale.y.to_bits()),
+        subpixel: (step_x, step_y),
+        foreground: face
+            .shaper
+            .is_color_glyph(rustybuzz::ttf_parser::GlyphId(id.0))
+            .then_some(foreground),
+    };
+
+    let mut cache = CACHE.lock().expect("Glyph cache lock poisoned");
+
+    let bitmap = match cache.get(&key) {
+        Some(bitmap) => bitmap.clone(),
+        None => {
+            let offset = |step: u8| f32::from(step) / SUBPIXEL_STEPS as f32;
+            let bitmap = draw(
+                face,
+                glyph
+                    .unpositioned()
+                    .clone()
+                    .positioned(point(offset(step_x), offset(step_y))),
+                foreground,
+            )
+            .map(Arc::new);
+
+            if cache.len() >= MAX_GLYPHS {
+                cache.clear();
+            }
+            cache.insert(key, bitmap.clone());
+            bitmap
+        }
+    }?;
+
+    Some((x + bitmap.left, y + bitmap.top, bitmap))
+}
+
+/// Rasterizes a glyph positioned within the first pixel, in its own colors when the font
+/// has some for it.
+fn draw(
+    face: &'static FontFace,
+    glyph: PositionedGlyph<'static>,
+    foreground: RgbColor,
+) -> Option<GlyphBitmap> {
+    if let Some(bitmap) = color_glyph::draw(face, &glyph, foreground) {
+        return Some(bitmap);
+    }
+
+    let bb = glyph.pixel_bounding_box()?;
+    let width = bb.width() as usize;
+    let mut coverage = vec![0.0; width * bb.height() as usize];
+
+    glyph.draw(|x, y, alpha| coverage[y as usize * width + x as usize] = alpha);
+
+    Some(GlyphBitmap {
+        width,
+        pixels: Pixels::Coverage(coverage),
+        left: bb.min.x,
+        top: bb.min.y,
+    })
+}
@@ -1,63 +1,214 @@
-use rusttype::{Scale, point};
+use std::sync::Mutex;
 
-use crate::configuration::global_font;
+use rusttype::Scale;
+use serde::Deserialize;
+
+use crate::{
+    configuration::FontConfig,
+    fonts::{self, FontChain},
+    layout::Direction,
+};
 
 use super::{
     BoundingBox, Component,
     colors::{ArgbColor, RgbColor},
+    glyph_cache::{self, Pixels},
+    text,
 };
 
+/// How a label is drawn on a vertical bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerticalText {
+    /// The text line turned 90° clockwise, read from top to bottom.
+    #[default]
+    Rotated,
+    /// Upright glyphs stacked one below the other, each centered across the bar.
+    Stacked,
+}
+
+/// The longest a label may be along its text; longer text is truncated with an ellipsis.
+///
+/// Written `{ pixels = 300 }` or `{ chars = 40 }` in the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaxWidth {
+    /// Logical pixels, multiplied by the scale factor of the output.
+    Pixels(u32),
+    /// Characters, combining marks and joined emoji counting with the character they
+    /// attach to.
+    Chars(usize),
+}
+
+/// Where text longer than the [`MaxWidth`] of its label is cut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+    /// The end is replaced with an ellipsis: `A long titl…`.
+    #[default]
+    End,
+    /// The middle is replaced with an ellipsis, keeping both ends: `A long…title`.
+    Middle,
+    /// The start is replaced with an ellipsis: `…ng title`.
+    Start,
+}
+
 #[allow(unused)]
 pub struct Label {
     pub text: String,
     pub fg_color: RgbColor,
     pub bg_color: Option<RgbColor>,
     pub font_size: u32,
+    pub fonts: FontChain,
+    pub vertical_text: VerticalText,
+    pub max_width: Option<MaxWidth>,
+    pub truncation: Truncation,
+    dirty: bool,
+    /// The last render, reused as long as nothing it depends on changes.
+    cache: Mutex<Option<(RenderKey, Rendered)>>,
+}
+
+type Rendered = (BoundingBox, Vec<Option<ArgbColor>>);
+
+/// Everything a rendered label depends on.
+#[derive(PartialEq)]
+struct RenderKey {
+    text: String,
+    fg_color: RgbColor,
+    bg_color: Option<RgbColor>,
+    font_size: u32,
+    /// Address of the font chain, which lives for the whole program.
+    fonts: usize,
+    vertical_text: VerticalText,
+    max_width: Option<MaxWidth>,
+    truncation: Truncation,
+    scale: u32,
+    direction: Direction,
 }
 
-#[allow(unused)]
 impl Label {
-    fn update(&mut self, text: &str) {
-        self.text = text.to_owned();
+    pub fn new(text: &str, fg_color: RgbColor, bg_color: Option<RgbColor>, font_size: u32) -> Self {
+        Self {
+            text: text.to_owned(),
+            fg_color,
+            bg_color,
+            font_size,
+            fonts: fonts::resolve(&FontConfig::default()),
+            vertical_text: VerticalText::default(),
+            max_width: None,
+            truncation: Truncation::default(),
+            dirty: true,
+            cache: Mutex::new(None),
+        }
     }
-}
 
-impl Component for Label {
-    fn render(&self) -> (BoundingBox, Vec<Option<ArgbColor>>) {
-        let scale = Scale::uniform(self.font_size as f32);
-        let v_metrics = global_font().v_metrics(scale);
-        let glyphs: Vec<_> = global_font()
-            .layout(&self.text, scale, point(0.0, v_metrics.ascent))
+    pub fn with_fonts(mut self, fonts: FontChain) -> Self {
+        self.fonts = fonts;
+        self
+    }
+
+    pub fn with_vertical_text(mut self, vertical_text: VerticalText) -> Self {
+        self.vertical_text = vertical_text;
+        self
+    }
+
+    /// Truncates the text beyond `max_width`, where `truncation` tells.
+    pub fn with_max_width(mut self, max_width: Option<MaxWidth>, truncation: Truncation) -> Self {
+        self.max_width = max_width;
+        self.truncation = truncation;
+        self
+    }
+
+    /// Replaces the text, marking the label dirty only when it actually changed.
+    pub fn update(&mut self, text: &str) {
+        if self.text != text {
+            self.text = text.to_owned();
+            self.dirty = true;
+        }
+    }
+
+    /// The text as drawn: whole, or truncated to fit within `max_width` once laid out for
+    /// `direction`.
+    fn displayed_text(&self, scale: f32, direction: Direction) -> String {
+        let Some(max_width) = self.max_width else {
+            return self.text.clone();
+        };
+
+        text::truncate(&self.text, self.truncation, |text| match max_width {
+            MaxWidth::Chars(max) => text::character_count(text) <= max,
+            MaxWidth::Pixels(max) => self.length(text, scale, direction) <= max as f32 * scale,
+        })
+    }
+
+    /// Length in pixels of `text` along its line: the width of the line, or the height of the
+    /// stack for stacked vertical text.
+    fn length(&self, text: &str, scale: f32, direction: Direction) -> f32 {
+        let scale = Scale::uniform(self.font_size as f32 * scale);
+
+        match (direction, self.vertical_text) {
+            (Direction::Column, VerticalText::Stacked) => text::split_characters(text)
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| text::layout(self.fonts, c, scale).height.ceil().max(1.0))
+                .sum(),
+            _ => text::layout(self.fonts, text, scale).width.ceil(),
+        }
+    }
+
+    /// Renders `text` as a single horizontal line.
+    fn render_line(&self, text: &str, scale: f32) -> Rendered {
+        let scale = Scale::uniform(self.font_size as f32 * scale);
+        let line = text::layout(self.fonts, text, scale);
+        let glyphs: Vec<_> = line
+            .glyphs
+            .iter()
+            .filter_map(|(face, glyph)| glyph_cache::rasterize(face, glyph, self.fg_color))
             .collect();
 
         let width = glyphs
             .iter()
-            .map(|g| g.pixel_bounding_box().map(|bb| bb.max.x).unwrap_or(0))
+            .map(|(left, _, bitmap)| left + bitmap.width as i32)
             .max()
             .unwrap_or(0)
             .max(1) as usize;
 
-        let height = (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap)
-            .ceil()
-            .max(1.0) as usize;
+        let height = line.height.ceil().max(1.0) as usize;
 
         let mut buffer: Vec<Option<ArgbColor>> = vec![None; width * height];
 
-        for glyph in glyphs {
-            if let Some(bb) = glyph.pixel_bounding_box() {
-                glyph.draw(|x, y, alpha| {
-                    let x = x as i32 + bb.min.x;
-                    let y = y as i32 + bb.min.y;
-                    if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-                        let idx = (y as usize * width + x as usize) as usize;
-                        if alpha > 0.0 {
+        for (left, top, bitmap) in glyphs {
+            let index = |i: usize| {
+                let x = left + (i % bitmap.width) as i32;
+                let y = top + (i / bitmap.width) as i32;
+                (x >= 0 && x < width as i32 && y >= 0 && y < height as i32)
+                    .then(|| y as usize * width + x as usize)
+            };
+
+            match &bitmap.pixels {
+                Pixels::Coverage(coverage) => {
+                    for (i, &alpha) in coverage.iter().enumerate() {
+                        if let Some(idx) = index(i)
+                            && alpha > 0.0
+                        {
                             let pixel =
                                 ArgbColor::default().set_rgb(self.fg_color).set_alpha(alpha);
 
                             buffer[idx] = Some(pixel);
                         }
                     }
-                });
+                }
+                // Color glyphs keep their own colors, over whatever they overlap
+                Pixels::Color(colors) => {
+                    for (i, &color) in colors.iter().enumerate() {
+                        if let Some(idx) = index(i)
+                            && color.alpha > 0.0
+                        {
+                            buffer[idx] = Some(match buffer[idx] {
+                                Some(under) => color.blend_with_background(under),
+                                None => color,
+                            });
+                        }
+                    }
+                }
             }
         }
 
@@ -70,4 +221,121 @@ impl Component for Label {
             buffer,
         )
     }
+
+    /// Renders every character of `text` on its own line, centered horizontally.
+    fn render_stacked(&self, text: &str, scale: f32) -> Rendered {
+        let lines: Vec<_> = text::split_characters(text)
+            .filter(|c| !c.trim().is_empty())
+            .map(|c| self.render_line(c, scale))
+            .collect();
+
+        let width = lines.iter().map(|(bbox, _)| bbox.width).max().unwrap_or(1);
+        let height = lines
+            .iter()
+            .map(|(bbox, _)| bbox.height)
+            .sum::<u32>()
+            .max(1);
+
+        let mut buffer = vec![None; (width * height) as usize];
+        let mut top = 0;
+
+        for (bbox, pixels) in lines {
+            let left = (width - bbox.width) / 2;
+
+            for (y, row) in pixels.chunks_exact(bbox.width as usize).enumerate() {
+                let start = ((top + y as u32) * width + left) as usize;
+                buffer[start..start + row.len()].copy_from_slice(row);
+            }
+
+            top += bbox.height;
+        }
+
+        (BoundingBox { width, height }, buffer)
+    }
+
+    /// Paints `bg_color`, when set, under the whole rendered label.
+    fn fill_background(&self, (bbox, pixels): Rendered) -> Rendered {
+        let Some(background) = self.bg_color else {
+            return (bbox, pixels);
+        };
+        let background = ArgbColor::from(background);
+
+        let pixels = pixels
+            .into_iter()
+            .map(|pixel| {
+                Some(pixel.map_or(background, |pixel| pixel.blend_with_background(background)))
+            })
+            .collect();
+
+        (bbox, pixels)
+    }
+}
+
+/// Turns a rendered component 90° clockwise.
+fn rotate_clockwise((bbox, pixels): Rendered) -> Rendered {
+    let (width, height) = (bbox.width as usize, bbox.height as usize);
+    let mut rotated = vec![None; pixels.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            // The bottom row becomes the left column
+            rotated[x * height + (height - 1 - y)] = pixels[y * width + x];
+        }
+    }
+
+    (
+        BoundingBox {
+            width: bbox.height,
+            height: bbox.width,
+        },
+        rotated,
+    )
+}
+
+impl Component for Label {
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn clear_dirty(&mut self) {
+        self.dirty = false;
+    }
+
+    /// Renders the label, or returns the previous render when nothing it depends on
+    /// changed.
+    fn render(&self, scale: f32, direction: Direction) -> Rendered {
+        let key = RenderKey {
+            text: self.text.clone(),
+            fg_color: self.fg_color,
+            bg_color: self.bg_color,
+            font_size: self.font_size,
+            fonts: self.fonts.as_ptr() as usize,
+            vertical_text: self.vertical_text,
+            max_width: self.max_width,
+            truncation: self.truncation,
+            scale: scale.to_bits(),
+            direction,
+        };
+
+        let mut cache = self.cache.lock().expect("Label cache lock poisoned");
+
+        if let Some((cached_key, rendered)) = &*cache
+            && *cached_key == key
+        {
+            return rendered.clone();
+        }
+
+        let text = self.displayed_text(scale, direction);
+        let rendered = match (direction, self.vertical_text) {
+            (Direction::Row, _) => self.render_line(&text, scale),
+            (Direction::Column, VerticalText::Rotated) => {
+                rotate_clockwise(self.render_line(&text, scale))
+            }
+            (Direction::Column, VerticalText::Stacked) => self.render_stacked(&text, scale),
+        };
+        let rendered = self.fill_background(rendered);
+
+        *cache = Some((key, rendered.clone()));
+        rendered
+    }
 }
@@ -1,12 +1,25 @@
+mod color_glyph;
 mod colors;
+mod glyph_cache;
 mod label;
 mod padding;
+mod text;
+
+use std::any::Any;
+
+use crate::{configuration::ComponentConfig, fonts::FontChain, layout::Direction};
 
 #[allow(unused)]
 pub use colors::{ArgbColor, RgbColor};
-pub use label::Label;
+pub use label::{Label, MaxWidth, Truncation, VerticalText};
 pub use padding::Padding;
 
+/// The size of a rendered component in physical pixels, as laid out on the canvas.
+///
+/// `width` is always horizontal and `height` vertical, whatever the bar direction; the
+/// layout engine takes the main-axis size from `width` in a row and from `height` in a
+/// column. Components rendered for a column therefore return their already rotated or
+/// stacked size.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct BoundingBox {
     pub width: u32,
@@ -14,6 +27,58 @@ pub struct BoundingBox {
 }
 
 #[allow(unused)]
-pub trait Component {
-    fn render(&self) -> (BoundingBox, Vec<Option<ArgbColor>>);
+pub trait Component: Any {
+    /// Renders the component at `scale` physical pixels per logical pixel, for a bar laying
+    /// its components out along `direction`.
+    fn render(&self, scale: f32, direction: Direction) -> (BoundingBox, Vec<Option<ArgbColor>>);
+
+    /// Whether the component looks different than when it was last drawn.
+    ///
+    /// Components that never change keep the default `false`.
+    fn is_dirty(&self) -> bool {
+        false
+    }
+
+    /// Called once the component has been drawn in its current state.
+    fn clear_dirty(&mut self) {}
+}
+
+/// Creates the component described by a `[[widgets.*]]` configuration entry, drawing text
+/// with `fonts`.
+pub fn build_component(
+    config: &ComponentConfig,
+    fonts: FontChain,
+) -> Box<dyn Component + Send + Sync> {
+    match config {
+        ComponentConfig::Label {
+            text,
+            fg_color,
+            bg_color,
+            font_size,
+            vertical_text,
+            max_width,
+            truncate,
+            ..
+        } => Box::new(
+            Label::new(text, *fg_color, *bg_color, *font_size)
+                .with_fonts(fonts)
+                .with_vertical_text(*vertical_text)
+                .with_max_width(*max_width, *truncate),
+        ),
+        ComponentConfig::Padding { width, .. } => Box::new(Padding(*width)),
+        // The text is filled in by the clock module
+        ComponentConfig::Clock {
+            fg_color,
+            font_size,
+            vertical_text,
+            max_width,
+            truncate,
+            ..
+        } => Box::new(
+            Label::new("", *fg_color, None, *font_size)
+                .with_fonts(fonts)
+                .with_vertical_text(*vertical_text)
+                .with_max_width(*max_width, *truncate),
+        ),
+    }
 }
@@ -1,16 +1,16 @@
+use crate::layout::Direction;
+
 use super::{ArgbColor, BoundingBox, Component};
 
 #[allow(unused)]
 pub struct Padding(pub u32);
 
 impl Component for Padding {
-    fn render(&self) -> (BoundingBox, Vec<Option<ArgbColor>>) {
-        (
-            BoundingBox {
-                width: self.0,
-                height: 0,
-            },
-            vec![None; self.0 as usize],
-        )
+    fn render(&self, scale: f32, direction: Direction) -> (BoundingBox, Vec<Option<ArgbColor>>) {
+        // Empty space along the bar, whichever way it runs
+        let length = (self.0 as f32 * scale).round() as u32;
+        let (width, height) = direction.join(length, 0);
+
+        (BoundingBox { width, height }, Vec::new())
     }
 }
@@ -0,0 +1,250 @@
+use std::ops::Range;
+
+use rusttype::{GlyphId, PositionedGlyph, Scale, ScaledGlyph, point};
+use rustybuzz::UnicodeBuffer;
+use unicode_bidi::ParagraphBidiInfo;
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+use crate::fonts::{FontChain, FontFace};
+
+use super::Truncation;
+
+/// Put in place of the characters a truncated text leaves out.
+const ELLIPSIS: &str = "\u{2026}";
+
+/// A line of text shaped and laid out across the fonts of a [`FontChain`].
+pub struct Line {
+    /// Glyphs positioned on a common baseline, below the tallest ascent of the fonts used,
+    /// each with the face it was taken from.
+    pub glyphs: Vec<(&'static FontFace, PositionedGlyph<'static>)>,
+    /// Height of the line in pixels, from the highest ascent to the lowest descent among the
+    /// fonts used.
+    pub height: f32,
+    /// Width of the line in pixels, to the caret after the last glyph or to the right edge of
+    /// a glyph drawn further.
+    pub width: f32,
+}
+
+/// A glyph placed along the line, before the baseline is known.
+struct ShapedGlyph {
+    face: &'static FontFace,
+    glyph: ScaledGlyph<'static>,
+    x: f32,
+    /// Offset above the baseline.
+    rise: f32,
+}
+
+/// Shapes `text` and lays it out as a single line starting at the origin.
+///
+/// The text is split into runs of one direction, reordered for display with the Unicode
+/// bidirectional algorithm, then into runs of one font: every character is taken from the
+/// first font of `fonts` that has a glyph for it, or from the first font when none has, and
+/// combining marks stay with the font of their base. Each run is shaped with rustybuzz, which
+/// applies ligatures, kerning, contextual forms and mark positioning.
+pub fn layout(fonts: FontChain, text: &str, scale: Scale) -> Line {
+    // An empty line, which has no runs to lay out, still takes the height of the first font
+    if text.is_empty() {
+        let metrics = fonts[0].font.v_metrics(scale);
+        return Line {
+            glyphs: Vec::new(),
+            height: metrics.ascent - metrics.descent + metrics.line_gap,
+            width: 0.0,
+        };
+    }
+
+    let mut used = vec![false; fonts.len()];
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0;
+
+    let bidi = ParagraphBidiInfo::new(text, None);
+    let (levels, runs) = bidi.visual_runs(0..text.len());
+
+    for run in runs {
+        let rtl = levels[run.start].is_rtl();
+        let mut font_runs = font_runs(fonts, &text[run.clone()]);
+
+        // Right-to-left runs are displayed from their last character
+        if rtl {
+            font_runs.reverse();
+        }
+
+        for (index, range) in font_runs {
+            used[index] = true;
+            let text = &text[run.start + range.start..run.start + range.end];
+            caret = shape(fonts[index], text, rtl, scale, caret, &mut glyphs);
+        }
+    }
+
+    let metrics: Vec<_> = fonts
+        .iter()
+        .zip(used)
+        .filter(|(_, used)| *used)
+        .map(|(face, _)| face.font.v_metrics(scale))
+        .collect();
+    let ascent = metrics.iter().map(|m| m.ascent).fold(f32::MIN, f32::max);
+    let descent = metrics.iter().map(|m| m.descent).fold(f32::MAX, f32::min);
+    let line_gap = metrics.iter().map(|m| m.line_gap).fold(0.0, f32::max);
+
+    let glyphs: Vec<_> = glyphs
+        .into_iter()
+        .map(|shaped| {
+            let position = point(shaped.x, ascent - shaped.rise);
+            (shaped.face, shaped.glyph.positioned(position))
+        })
+        .collect();
+    let right = glyphs
+        .iter()
+        .filter_map(|(_, glyph)| glyph.pixel_bounding_box())
+        .map(|bb| bb.max.x as f32)
+        .fold(caret, f32::max);
+
+    Line {
+        glyphs,
+        height: ascent - descent + line_gap,
+        width: right,
+    }
+}
+
+/// Splits `text` into runs drawn with a single font of `fonts`, in logical order.
+fn font_runs(fonts: FontChain, text: &str) -> Vec<(usize, Range<usize>)> {
+    let mut runs: Vec<(usize, Range<usize>)> = Vec::new();
+
+    for (start, c) in text.char_indices() {
+        let end = start + c.len_utf8();
+
+        if let Some((index, range)) = runs.last_mut()
+            && is_attached(c)
+            && fonts[*index].has_glyph(c)
+        {
+            range.end = end;
+            continue;
+        }
+
+        let index = fonts.iter().position(|face| face.has_glyph(c)).unwrap_or(0);
+
+        match runs.last_mut() {
+            Some((last, range)) if *last == index => range.end = end,
+            _ => runs.push((index, start..end)),
+        }
+    }
+
+    runs
+}
+
+/// Shapes a run of `text` in a single font and direction, appending its glyphs from `caret`.
+///
+/// Returns the caret after the run.
+fn shape(
+    face: &'static FontFace,
+    text: &str,
+    rtl: bool,
+    scale: Scale,
+    mut caret: f32,
+    glyphs: &mut Vec<ShapedGlyph>,
+) -> f32 {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(match rtl {
+        true => rustybuzz::Direction::RightToLeft,
+        false => rustybuzz::Direction::LeftToRight,
+    });
+
+    let output = rustybuzz::shape(&face.shaper, &[], buffer);
+
+    let (scale_x, scale_y) = face.pixels_per_unit(scale);
+
+    for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+        glyphs.push(ShapedGlyph {
+            face,
+            glyph: face.font.glyph(GlyphId(info.glyph_id as u16)).scaled(scale),
+            x: caret + position.x_offset as f32 * scale_x,
+            rise: position.y_offset as f32 * scale_y,
+        });
+
+        caret += position.x_advance as f32 * scale_x;
+    }
+
+    caret
+}
+
+/// Whether `c` belongs to the preceding character: marks, joiners and variation selectors.
+fn is_attached(c: char) -> bool {
+    c.general_category_group() == GeneralCategoryGroup::Mark
+        || c == '\u{200D}'
+        || ('\u{FE00}'..='\u{FE0F}').contains(&c)
+}
+
+/// Byte offsets where the characters of `text` start, with the marks and joiners attached to
+/// them and the emoji joined after a zero width joiner.
+fn characters(text: &str) -> impl Iterator<Item = usize> {
+    let mut previous = None;
+
+    text.char_indices().filter_map(move |(start, c)| {
+        let joined = previous == Some('\u{200D}');
+        previous = Some(c);
+        (!is_attached(c) && !joined).then_some(start)
+    })
+}
+
+/// Splits `text` into its characters, each with the marks and joiners attached to it.
+pub fn split_characters(text: &str) -> impl Iterator<Item = &str> {
+    let mut start = 0;
+
+    characters(text)
+        .filter(|&start| start > 0)
+        .chain([text.len()])
+        .filter_map(move |end| {
+            let character = &text[start..end];
+            start = end;
+            (!character.is_empty()).then_some(character)
+        })
+}
+
+/// The number of characters in `text`, counting marks and joiners with the character they
+/// attach to.
+pub fn character_count(text: &str) -> usize {
+    characters(text).count()
+}
+
+/// Shortens `text` with an ellipsis where `truncation` tells, keeping as many whole
+/// characters as `fits` accepts; whitespace next to the ellipsis is dropped.
+///
+/// Returns `text` unchanged when it fits, and an empty string when not even the ellipsis
+/// alone does.
+pub fn truncate(text: &str, truncation: Truncation, fits: impl Fn(&str) -> bool) -> String {
+    if fits(text) {
+        return text.to_owned();
+    }
+
+    let starts: Vec<_> = characters(text).chain([text.len()]).collect();
+    let count = starts.len() - 1;
+
+    let shortened = |kept: usize| {
+        let (head, tail) = match truncation {
+            Truncation::End => (kept, 0),
+            Truncation::Middle => (kept.div_ceil(2), kept / 2),
+            Truncation::Start => (0, kept),
+        };
+        format!(
+            "{}{ELLIPSIS}{}",
+            text[..starts[head]].trim_end(),
+            text[starts[count - tail]..].trim_start(),
+        )
+    };
+
+    if !fits(&shortened(0)) {
+        return String::new();
+    }
+
+    // The most characters that fit, between `fitting` and `too_many`
+    let (mut fitting, mut too_many) = (0, count);
+    while too_many - fitting > 1 {
+        let kept = (fitting + too_many) / 2;
+        match fits(&shortened(kept)) {
+            true => fitting = kept,
+            false => too_many = kept,
+        }
+    }
+
+    shortened(fitting)
+}
@@ -0,0 +1,77 @@
+use serde::Deserialize;
+
+/// The font of the bar text, the `[theme.font]` table.
+///
+/// ```toml
+/// [theme.font]
+/// family = "JetBrains Mono"  # looked up among the installed fonts
+/// weight = "bold"            # 1 to 1000, or a name such as "light" or "bold"
+/// style = "italic"           # "normal", "italic" or "oblique"
+/// fallback = ["Noto Sans CJK JP", "Noto Color Emoji"]
+/// ```
+///
+/// Characters missing from `family` are taken from the first `fallback` family that has
+/// them, in the same weight and style, then from the font embedded in the binary. Without a
+/// `family`, or when it is not installed, the embedded font takes its place.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    pub family: Option<String>,
+    pub weight: FontWeight,
+    pub style: FontStyle,
+    pub fallback: Vec<String>,
+}
+
+/// The weight of a font, from 1 (thinnest) to 1000 (boldest); 400 is the regular weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "WeightValue")]
+pub struct FontWeight(pub u16);
+
+impl Default for FontWeight {
+    fn default() -> Self {
+        Self(400)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WeightValue {
+    Number(u16),
+    Name(String),
+}
+
+impl TryFrom<WeightValue> for FontWeight {
+    type Error = String;
+
+    fn try_from(value: WeightValue) -> Result<Self, Self::Error> {
+        let weight = match value {
+            WeightValue::Number(weight @ 1..=1000) => weight,
+            WeightValue::Number(weight) => {
+                return Err(format!("font weight {weight} is not between 1 and 1000"));
+            }
+            WeightValue::Name(name) => match name.as_str() {
+                "thin" => 100,
+                "extra_light" => 200,
+                "light" => 300,
+                "normal" | "regular" => 400,
+                "medium" => 500,
+                "semi_bold" => 600,
+                "bold" => 700,
+                "extra_bold" => 800,
+                "black" => 900,
+                _ => return Err(format!("unknown font weight `{name}`")),
+            },
+        };
+
+        Ok(Self(weight))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
@@ -0,0 +1,172 @@
+use std::{
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::Config;
+
+/// An error raised while locating, reading or parsing the configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file exists (or was requested explicitly) but cannot be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The file is not valid TOML or holds a value `SimBar` cannot accept.
+    ///
+    /// `line` and `column` are 1-based and point at the offending value when known.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Self::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{line}:{column}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME/simbar/config.toml`, falling back to `$HOME/.config` when
+/// `XDG_CONFIG_HOME` is unset or empty.
+pub fn default_config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("simbar").join("config.toml"))
+}
+
+/// Loads the configuration from `path`, or from [`default_config_path`] when `path` is `None`.
+///
+/// A missing file at the default location is not an error: the built-in defaults are used
+/// instead. A file passed explicitly (e.g. through `--config`) must exist.
+pub fn load_config(path: Option<&Path>) -> Result<Config, ConfigError> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => match default_config_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(source) => parse_config(&path, &source),
+        Err(err) if err.kind() == io::ErrorKind::NotFound && !explicit => Ok(Config::default()),
+        Err(source) => Err(ConfigError::Io { path, source }),
+    }
+}
+
+/// Parses and validates the content of a configuration file.
+///
+/// `path` is only used to label errors.
+pub fn parse_config(path: &Path, source: &str) -> Result<Config, ConfigError> {
+    toml::from_str(source).map_err(|err| {
+        let (line, column) = err
+            .span()
+            .map_or((1, 1), |span| line_column(source, span.start));
+
+        ConfigError::Parse {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message: err.message().to_owned(),
+        }
+    })
+}
+
+/// Converts a byte offset in `source` to a 1-based `(line, column)` pair.
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |current| current.chars().count())
+        + 1;
+
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `source` and returns the location and message of the error it must raise.
+    fn parse_error(source: &str) -> (usize, usize, String) {
+        match parse_config(Path::new("config.toml"), source) {
+            Err(ConfigError::Parse {
+                line,
+                column,
+                message,
+                ..
+            }) => (line, column, message),
+            other => panic!("Expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn wrong_type_points_at_the_value() {
+        let (line, column, message) =
+            parse_error("[simbar]\nanchor = \"top\"\nheight = \"tall\"\n");
+
+        assert_eq!((line, column), (3, 10));
+        assert!(message.contains("u32"), "{message}");
+    }
+
+    #[test]
+    fn zero_height_is_rejected() {
+        let (line, column, message) = parse_error("[simbar]\nheight = 0\n");
+
+        assert_eq!((line, column), (2, 10));
+        assert_eq!(message, "value must be greater than 0");
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        let (line, column, message) = parse_error("[simbar]\n  heigth = 30\n");
+
+        assert_eq!((line, column), (2, 3));
+        assert!(message.contains("unknown field `heigth`"), "{message}");
+    }
+
+    #[test]
+    fn deprecated_frame_rate_still_loads() {
+        let config = parse_config(Path::new("config.toml"), "[simbar]\nframe_rate = 24\n");
+
+        assert_eq!(config.unwrap().simbar, Config::default().simbar);
+    }
+
+    #[test]
+    fn location_counts_characters() {
+        assert_eq!(line_column("a = \"é\"\nb", 9), (2, 1));
+        assert_eq!(line_column("a = \"é\"", 7), (1, 7));
+    }
+
+    #[test]
+    fn error_is_displayed_with_its_location() {
+        let err = parse_config(Path::new("config.toml"), "[simbar]\nheight = 0\n").unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "config.toml:2:10: value must be greater than 0"
+        );
+    }
+}
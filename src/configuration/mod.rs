@@ -0,0 +1,239 @@
+mod font;
+mod loader;
+mod outputs;
+mod watcher;
+mod widgets;
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{Error, IgnoredAny},
+};
+use smithay_client_toolkit::output::OutputInfo;
+
+use crate::{
+    components::ArgbColor,
+    layout::{Direction, Margin},
+};
+
+pub use font::{FontConfig, FontStyle, FontWeight};
+pub use loader::{ConfigError, default_config_path, load_config};
+pub use outputs::{OutputOverride, OutputSelection};
+pub use watcher::ConfigWatcher;
+pub use widgets::{ComponentConfig, WidgetsConfig};
+
+/// The whole configuration file, as read from `config.toml`.
+///
+/// Every section is optional; missing sections and fields fall back to their defaults.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Geometry of the bar, the `[simbar]` section.
+    pub simbar: SimbarConfig,
+    /// Colors of the bar, the `[theme]` section.
+    pub theme: ThemeConfig,
+    /// Components of the left, center and right regions, the `[widgets]` section.
+    pub widgets: WidgetsConfig,
+    /// Per-output overrides, the `[[output]]` sections.
+    #[serde(rename = "output")]
+    pub overrides: Vec<OutputOverride>,
+}
+
+impl Config {
+    /// The configuration of the bar on the output described by `info`, with every matching
+    /// `[[output]]` section applied in order.
+    pub fn for_output(&self, info: &OutputInfo) -> Config {
+        let mut config = self.clone();
+
+        for section in self
+            .overrides
+            .iter()
+            .filter(|section| section.matches(info))
+        {
+            section.apply(&mut config);
+        }
+
+        config
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimbarConfig {
+    /// Length used when the output reports no logical size.
+    #[serde(deserialize_with = "non_zero")]
+    pub width_fallback: u32,
+    /// Length of the bar along its edge; defaults to the output length.
+    #[serde(deserialize_with = "non_zero_option")]
+    pub width: Option<u32>,
+    /// Thickness of the bar, across its edge.
+    #[serde(deserialize_with = "non_zero")]
+    pub height: u32,
+    pub primary_output: Option<String>,
+    pub outputs: OutputSelection,
+    /// The output edge the bar is attached to.
+    pub anchor: Edge,
+    pub layer: BarLayer,
+    /// Space between the bar and the output edges it is anchored to.
+    pub margin: Margin,
+    pub exclusive_zone: ExclusiveZone,
+    /// Deprecated: accepted so older configurations still load, but ignored as the bar only
+    /// redraws when something changes.
+    #[serde(deserialize_with = "deprecated_frame_rate")]
+    frame_rate: (),
+}
+
+/// An output edge, the `anchor` of the bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// The layer-shell layer the bar is drawn on, from the bottom-most to the top-most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BarLayer {
+    Background,
+    Bottom,
+    Top,
+    #[default]
+    Overlay,
+}
+
+/// How the bar interacts with the space reserved by other surfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusiveZone {
+    /// Reserve the thickness of the bar so windows do not cover it.
+    #[default]
+    Auto,
+    /// Reserve nothing, but stay clear of the space reserved by other surfaces.
+    None,
+    /// Reserve nothing and ignore the space reserved by other surfaces.
+    Ignore,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub background_color: ArgbColor,
+    /// The font of every label, the `[theme.font]` table.
+    pub font: FontConfig,
+}
+
+// -------------------------------------------------------------------------------------------------------
+
+impl Default for SimbarConfig {
+    fn default() -> Self {
+        Self {
+            width_fallback: 1920,
+            width: None,
+            height: 40,
+            primary_output: None,
+            outputs: OutputSelection::default(),
+            anchor: Edge::default(),
+            layer: BarLayer::default(),
+            margin: Margin::default(),
+            exclusive_zone: ExclusiveZone::default(),
+            frame_rate: (),
+        }
+    }
+}
+
+impl SimbarConfig {
+    /// Returns the `(width, height)` of the bar surface on an output.
+    ///
+    /// Bars on the left and right edges are `height` wide and as tall as the output. Without
+    /// a configured `width`, the bar spans the output less the margins along its edge; the
+    /// compositor has the last word on that length, see [`Monitor::place`].
+    ///
+    /// [`Monitor::place`]: crate::simbar::Monitor::place
+    pub fn bar_size(&self, info: &OutputInfo) -> (u32, u32) {
+        let direction = self.anchor.direction();
+        let (margin_start, margin_end, _, _) = direction.split_margin(self.margin);
+
+        let output_length = info.logical_size.map_or(self.width_fallback, |size| {
+            direction.split((size.0 as u32, size.1 as u32)).0
+        });
+        let length = output_length
+            .saturating_sub(margin_start + margin_end)
+            .max(1);
+
+        direction.join(self.width.unwrap_or(length), self.height)
+    }
+
+    /// Returns the `(width, height)` of the bar when no output is known, e.g. when rendering
+    /// without a compositor.
+    pub fn fallback_size(&self) -> (u32, u32) {
+        self.anchor
+            .direction()
+            .join(self.width.unwrap_or(self.width_fallback), self.height)
+    }
+
+    /// The exclusive zone requested from the compositor.
+    pub fn exclusive_zone(&self) -> i32 {
+        match self.exclusive_zone {
+            ExclusiveZone::Auto => self.height as i32,
+            ExclusiveZone::None => 0,
+            ExclusiveZone::Ignore => -1,
+        }
+    }
+
+    /// Whether the bar is shown on this output, according to `outputs`.
+    pub fn shows_on(&self, info: &OutputInfo) -> bool {
+        self.outputs.matches(info, || self.is_primary(info))
+    }
+
+    /// Whether this output is the primary one.
+    ///
+    /// Without `primary_output`, the output placed at `(0, 0)` is the primary one.
+    pub fn is_primary(&self, info: &OutputInfo) -> bool {
+        self.primary_output
+            .as_deref()
+            .map_or(info.logical_position == Some((0, 0)), |name| {
+                info.name.as_deref() == Some(name)
+            })
+    }
+}
+
+impl Edge {
+    /// The axis the bar content is laid out along.
+    pub fn direction(self) -> Direction {
+        match self {
+            Self::Top | Self::Bottom => Direction::Row,
+            Self::Left | Self::Right => Direction::Column,
+        }
+    }
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            background_color: ArgbColor::new(0.6, 0x11, 0x11, 0x18),
+            font: FontConfig::default(),
+        }
+    }
+}
+
+fn non_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    match u32::deserialize(deserializer)? {
+        0 => Err(D::Error::custom("value must be greater than 0")),
+        value => Ok(value),
+    }
+}
+
+fn non_zero_option<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    non_zero(deserializer).map(Some)
+}
+
+fn deprecated_frame_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    IgnoredAny::deserialize(deserializer)?;
+    eprintln!(
+        "`frame_rate` is deprecated and ignored, the bar redraws only when something changes"
+    );
+    Ok(())
+}
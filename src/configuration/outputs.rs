@@ -0,0 +1,188 @@
+use serde::Deserialize;
+use smithay_client_toolkit::output::OutputInfo;
+
+use crate::components::ArgbColor;
+
+use super::{ComponentConfig, Config, Edge, FontConfig};
+
+/// The outputs the bar is shown on, the `outputs` key of the `[simbar]` section.
+///
+/// ```toml
+/// outputs = "primary"                  # only the primary output (default)
+/// outputs = "all"                      # every output
+/// outputs = ["DP-1", "HDMI-A-1"]       # outputs with one of these names
+/// outputs = { glob = "*Dell*" }        # outputs whose name or description matches
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(
+    untagged,
+    expecting = r#""all", "primary", a list of output names or { glob = "<pattern>" }"#
+)]
+pub enum OutputSelection {
+    Mode(OutputMode),
+    Names(Vec<String>),
+    Glob { glob: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    All,
+    Primary,
+}
+
+impl Default for OutputSelection {
+    fn default() -> Self {
+        Self::Mode(OutputMode::Primary)
+    }
+}
+
+impl OutputSelection {
+    /// Whether the bar is shown on the output described by `info`.
+    ///
+    /// `is_primary` is only evaluated in the `primary` mode.
+    pub fn matches(&self, info: &OutputInfo, is_primary: impl FnOnce() -> bool) -> bool {
+        match self {
+            Self::Mode(OutputMode::All) => true,
+            Self::Mode(OutputMode::Primary) => is_primary(),
+            Self::Names(names) => info.name.as_ref().is_some_and(|name| names.contains(name)),
+            Self::Glob { glob } => [&info.name, &info.description]
+                .into_iter()
+                .flatten()
+                .any(|text| glob_match(glob, text)),
+        }
+    }
+}
+
+/// Matches `text` against a shell-like pattern where `*` stands for any sequence of
+/// characters and `?` for a single one.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and of the text it started matching at
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    p = star + 1;
+                    t = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Settings applied to the outputs matching its criteria, a `[[output]]` section.
+///
+/// Every criterion is a pattern as in `{ glob = "..." }`; all the given ones must match and
+/// a section without criteria applies to every output. Sections are applied in file order,
+/// each overriding only the keys it sets:
+///
+/// ```toml
+/// [[output]]
+/// name = "eDP-*"
+/// height = 30
+/// anchor = "bottom"
+/// theme = { background_color = "#CC000000" }
+///
+/// [[output.widgets.right]]
+/// type = "clock"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputOverride {
+    pub name: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "super::non_zero_option")]
+    pub height: Option<u32>,
+    pub anchor: Option<Edge>,
+    #[serde(default)]
+    pub theme: ThemeOverride,
+    #[serde(default)]
+    pub widgets: WidgetsOverride,
+}
+
+/// The `theme` keys of an `[[output]]` section.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeOverride {
+    pub background_color: Option<ArgbColor>,
+    /// Replaces the whole `[theme.font]` table, keys left out take their default.
+    pub font: Option<FontConfig>,
+}
+
+/// The `widgets` regions of an `[[output]]` section; each one given replaces the whole region.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetsOverride {
+    pub left: Option<Vec<ComponentConfig>>,
+    pub center: Option<Vec<ComponentConfig>>,
+    pub right: Option<Vec<ComponentConfig>>,
+}
+
+impl OutputOverride {
+    /// Whether the section applies to the output described by `info`.
+    pub fn matches(&self, info: &OutputInfo) -> bool {
+        let criteria = [
+            (&self.name, info.name.as_deref()),
+            (&self.make, Some(info.make.as_str())),
+            (&self.model, Some(info.model.as_str())),
+            (&self.description, info.description.as_deref()),
+        ];
+
+        criteria.into_iter().all(|(pattern, value)| match pattern {
+            Some(pattern) => value.is_some_and(|value| glob_match(pattern, value)),
+            None => true,
+        })
+    }
+
+    /// Overrides the keys this section sets in `config`.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(height) = self.height {
+            config.simbar.height = height;
+        }
+
+        if let Some(anchor) = self.anchor {
+            config.simbar.anchor = anchor;
+        }
+
+        if let Some(background_color) = self.theme.background_color {
+            config.theme.background_color = background_color;
+        }
+
+        if let Some(font) = &self.theme.font {
+            config.theme.font.clone_from(font);
+        }
+
+        let regions = [
+            (&self.widgets.left, &mut config.widgets.left),
+            (&self.widgets.center, &mut config.widgets.center),
+            (&self.widgets.right, &mut config.widgets.right),
+        ];
+
+        for (components, region) in regions {
+            if let Some(components) = components {
+                region.clone_from(components);
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    ffi::OsString,
+    io,
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+};
+
+use inotify::{Inotify, WatchMask};
+
+/// Watches the configuration file for modifications through inotify.
+///
+/// The parent directory is watched rather than the file itself, so editors that save by
+/// writing a temporary file and renaming it over the original are detected too. The inotify
+/// descriptor is non-blocking and meant to be registered on the event loop.
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    file_name: OsString,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+            .to_owned();
+
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+
+        let inotify = Inotify::init()?;
+        inotify
+            .watches()
+            .add(directory, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)?;
+
+        Ok(Self { inotify, file_name })
+    }
+
+    /// Drains the pending inotify events and returns whether any of them touched the
+    /// configuration file.
+    pub fn changed(&mut self) -> bool {
+        let mut buffer = [0; 4096];
+        let mut changed = false;
+
+        loop {
+            match self.inotify.read_events(&mut buffer) {
+                Ok(events) => {
+                    for event in events {
+                        changed |= event.name == Some(self.file_name.as_os_str());
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    eprintln!("Failed to read configuration events: {err}");
+                    break;
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+impl AsFd for ConfigWatcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+}
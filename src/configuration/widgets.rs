@@ -0,0 +1,161 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::components::{MaxWidth, RgbColor, Truncation, VerticalText};
+
+/// The components shown in each region of the bar, the `[widgets]` section.
+///
+/// Each region is an ordered list of components, e.g.
+///
+/// ```toml
+/// [[widgets.center]]
+/// type = "label"
+/// id = "title"
+/// text = "Hello"
+/// ```
+///
+/// Without a `[widgets]` section the bar shows a placeholder; once the section exists, the
+/// regions it leaves out are empty.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WidgetsConfig {
+    #[serde(default)]
+    pub left: Vec<ComponentConfig>,
+    #[serde(default)]
+    pub center: Vec<ComponentConfig>,
+    #[serde(default)]
+    pub right: Vec<ComponentConfig>,
+}
+
+/// A single component of a widget region, selected by its `type` key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ComponentConfig {
+    Label {
+        /// Name used by modules to find and update this label.
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        text: String,
+        #[serde(default)]
+        fg_color: RgbColor,
+        #[serde(default)]
+        bg_color: Option<RgbColor>,
+        #[serde(default = "default_font_size")]
+        font_size: u32,
+        /// How the text is drawn on bars anchored to the left or right edge.
+        #[serde(default)]
+        vertical_text: VerticalText,
+        /// The longest the text may be, `{ pixels = 300 }` or `{ chars = 40 }`.
+        #[serde(default)]
+        max_width: Option<MaxWidth>,
+        /// Where text longer than `max_width` is cut and replaced with an ellipsis.
+        #[serde(default)]
+        truncate: Truncation,
+    },
+    Padding {
+        #[serde(default)]
+        id: Option<String>,
+        width: u32,
+    },
+    /// A label showing the current time, refreshed by the clock module.
+    Clock {
+        #[serde(default)]
+        id: Option<String>,
+        /// A `strftime` format, e.g. `%a %d %b %H:%M`.
+        #[serde(default = "default_clock_format", deserialize_with = "strftime")]
+        format: String,
+        /// IANA time zones shown side by side, e.g. `["Europe/Paris", "UTC"]`.
+        ///
+        /// Empty shows the local time.
+        #[serde(default)]
+        timezones: Vec<Tz>,
+        /// Text put between two time zones.
+        #[serde(default = "default_clock_separator")]
+        separator: String,
+        #[serde(default)]
+        fg_color: RgbColor,
+        #[serde(default = "default_font_size")]
+        font_size: u32,
+        /// How the text is drawn on bars anchored to the left or right edge.
+        #[serde(default)]
+        vertical_text: VerticalText,
+        /// The longest the text may be, `{ pixels = 300 }` or `{ chars = 40 }`.
+        #[serde(default)]
+        max_width: Option<MaxWidth>,
+        /// Where text longer than `max_width` is cut and replaced with an ellipsis.
+        #[serde(default)]
+        truncate: Truncation,
+    },
+}
+
+impl ComponentConfig {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Label { id, .. } | Self::Padding { id, .. } | Self::Clock { id, .. } => {
+                id.as_deref()
+            }
+        }
+    }
+}
+
+fn default_font_size() -> u32 {
+    25
+}
+
+fn default_clock_format() -> String {
+    "%H:%M".to_owned()
+}
+
+fn default_clock_separator() -> String {
+    "  ".to_owned()
+}
+
+/// Rejects formats chrono cannot render, which would otherwise panic on every refresh.
+fn strftime<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let format = String::deserialize(deserializer)?;
+
+    if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+        return Err(D::Error::custom(format!(
+            "`{format}` is not a valid strftime format"
+        )));
+    }
+
+    Ok(format)
+}
+
+impl Default for WidgetsConfig {
+    fn default() -> Self {
+        Self {
+            left: Vec::new(),
+            center: vec![
+                ComponentConfig::Label {
+                    id: None,
+                    text: "Hello".to_owned(),
+                    fg_color: RgbColor::new(0xFF, 0xFF, 0xFF),
+                    bg_color: None,
+                    font_size: default_font_size(),
+                    vertical_text: VerticalText::default(),
+                    max_width: None,
+                    truncate: Truncation::default(),
+                },
+                ComponentConfig::Padding {
+                    id: None,
+                    width: 20,
+                },
+                ComponentConfig::Label {
+                    id: None,
+                    text: "World".to_owned(),
+                    fg_color: RgbColor::new(0xFF, 0x00, 0xFF),
+                    bg_color: None,
+                    font_size: default_font_size(),
+                    vertical_text: VerticalText::default(),
+                    max_width: None,
+                    truncate: Truncation::default(),
+                },
+            ],
+            right: Vec::new(),
+        }
+    }
+}
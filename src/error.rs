@@ -0,0 +1,140 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+use smithay_client_toolkit::{
+    reexports::calloop,
+    shm::{
+        CreatePoolError,
+        slot::{ActivateSlotError, CreateBufferError},
+    },
+};
+use wayland_client::{
+    ConnectError,
+    globals::{BindError, GlobalError},
+};
+
+use crate::configuration::ConfigError;
+
+/// An error that stops `SimBar`, or the bar on one output.
+#[derive(Debug)]
+pub enum SimbarError {
+    /// No Wayland compositor could be reached.
+    Connect(ConnectError),
+    /// The initial roundtrip listing the globals of the compositor failed.
+    Registry(GlobalError),
+    /// The compositor does not advertise a global `SimBar` needs.
+    MissingGlobal {
+        interface: &'static str,
+        source: BindError,
+    },
+    /// The shared memory pool of a bar could not be allocated.
+    CreatePool(CreatePoolError),
+    /// The shared memory pool of a bar could not be grown to fit a new buffer.
+    ResizePool(io::Error),
+    /// A buffer could not be carved out of the shared memory pool.
+    CreateBuffer(CreateBufferError),
+    /// A buffer could not be attached to its surface.
+    AttachBuffer(ActivateSlotError),
+    /// The event loop could not be set up.
+    EventLoop(calloop::Error),
+    /// The connection to the compositor failed while running, e.g. because it crashed.
+    ConnectionLost(calloop::Error),
+    /// The configuration file cannot be used.
+    Config(ConfigError),
+    /// A headless render could not be written out.
+    WritePng { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for SimbarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connect(err) => write!(f, "cannot connect to the Wayland compositor: {err}"),
+            Self::Registry(err) => write!(f, "cannot list the Wayland globals: {err}"),
+            Self::MissingGlobal { interface, source } => {
+                write!(f, "the compositor does not support {interface}: {source}")
+            }
+            Self::CreatePool(err) => write!(f, "cannot allocate shared memory: {err}"),
+            Self::ResizePool(err) => write!(f, "cannot grow shared memory: {err}"),
+            Self::CreateBuffer(err) => write!(f, "cannot create a buffer: {err}"),
+            Self::AttachBuffer(err) => write!(f, "cannot attach a buffer: {err}"),
+            Self::EventLoop(err) => write!(f, "event loop failure: {err}"),
+            Self::ConnectionLost(err) => {
+                write!(f, "lost the connection to the Wayland compositor: {err}")
+            }
+            Self::Config(err) => write!(f, "invalid configuration: {err}"),
+            Self::WritePng { path, source } => {
+                write!(f, "failed to write {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl SimbarError {
+    /// Whether the error comes from the compositor being gone, so connecting again later may
+    /// succeed.
+    pub fn is_disconnect(&self) -> bool {
+        matches!(
+            self,
+            Self::Connect(_) | Self::Registry(_) | Self::ConnectionLost(_)
+        )
+    }
+}
+
+impl Error for SimbarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Connect(err) => Some(err),
+            Self::Registry(err) => Some(err),
+            Self::MissingGlobal { source, .. } => Some(source),
+            Self::CreatePool(err) => Some(err),
+            Self::ResizePool(err) => Some(err),
+            Self::CreateBuffer(err) => Some(err),
+            Self::AttachBuffer(err) => Some(err),
+            Self::EventLoop(err) => Some(err),
+            Self::ConnectionLost(err) => Some(err),
+            Self::Config(err) => Some(err),
+            Self::WritePng { source, .. } => Some(source),
+        }
+    }
+}
+
+impl From<ConnectError> for SimbarError {
+    fn from(err: ConnectError) -> Self {
+        Self::Connect(err)
+    }
+}
+
+impl From<GlobalError> for SimbarError {
+    fn from(err: GlobalError) -> Self {
+        Self::Registry(err)
+    }
+}
+
+impl From<CreatePoolError> for SimbarError {
+    fn from(err: CreatePoolError) -> Self {
+        Self::CreatePool(err)
+    }
+}
+
+impl From<CreateBufferError> for SimbarError {
+    fn from(err: CreateBufferError) -> Self {
+        Self::CreateBuffer(err)
+    }
+}
+
+impl From<ActivateSlotError> for SimbarError {
+    fn from(err: ActivateSlotError) -> Self {
+        Self::AttachBuffer(err)
+    }
+}
+
+impl From<calloop::Error> for SimbarError {
+    fn from(err: calloop::Error) -> Self {
+        Self::EventLoop(err)
+    }
+}
+
+impl From<ConfigError> for SimbarError {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
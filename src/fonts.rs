@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, OnceLock},
+};
+
+use fontdb::{Database, Family, Query, Style, Weight};
+use rusttype::{Font, Scale};
+
+use crate::configuration::{FontConfig, FontStyle, FontWeight};
+
+/// A font face loaded both for rasterizing with rusttype and for shaping with rustybuzz,
+/// over the same data.
+pub struct FontFace {
+    pub font: Font<'static>,
+    pub shaper: rustybuzz::Face<'static>,
+}
+
+impl FontFace {
+    fn from_data(data: &'static [u8], index: u32) -> Option<Self> {
+        Some(Self {
+            font: Font::try_from_bytes_and_index(data, index)?,
+            shaper: rustybuzz::Face::from_slice(data, index)?,
+        })
+    }
+
+    /// Pixels per font unit, horizontally and vertically, for glyphs scaled to `scale`.
+    ///
+    /// rusttype scales a glyph so the font's extent, from the lowest descent to the highest
+    /// ascent, spans `scale` pixels, not its em.
+    pub fn pixels_per_unit(&self, scale: Scale) -> (f32, f32) {
+        let v_metrics = self.font.v_metrics_unscaled();
+        let extent = v_metrics.ascent - v_metrics.descent;
+        (scale.x / extent, scale.y / extent)
+    }
+
+    /// Whether the face has a glyph for `c`.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.shaper.glyph_index(c).is_some()
+    }
+}
+
+/// An ordered list of fonts; each character is drawn with the first one that has a glyph
+/// for it. Never empty, and always holds the [`embedded_font`].
+pub type FontChain = &'static [&'static FontFace];
+
+static EMBEDDED_FONT: OnceLock<FontFace> = OnceLock::new();
+
+/// The installed fonts, found through the fontconfig configuration and in the usual font
+/// directories (`/usr/share/fonts`, `~/.local/share/fonts`, ...). Only scanned when a font
+/// family is configured.
+static SYSTEM_FONTS: LazyLock<Database> = LazyLock::new(|| {
+    let mut database = Database::new();
+    database.load_system_fonts();
+    database
+});
+
+type FaceKey = (String, FontWeight, FontStyle);
+
+/// Faces already loaded, `None` when not installed. Kept for the whole program.
+static FACES: LazyLock<Mutex<HashMap<FaceKey, Option<&'static FontFace>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Chains already resolved, kept for the whole program.
+static CHAINS: LazyLock<Mutex<HashMap<FontConfig, FontChain>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The font embedded in the binary, used when no other font is configured or found.
+///
+/// Tests embed the font vendored in `tests/fonts/` instead, so that the reference images of
+/// the snapshot tests do not depend on the font shipped with the bar.
+pub fn embedded_font() -> &'static FontFace {
+    EMBEDDED_FONT.get_or_init(|| {
+        #[cfg(not(test))]
+        let font_data = include_bytes!("../resources/fonts/CodeNewRomanNerdFontMono-Regular.otf");
+        #[cfg(test)]
+        let font_data = include_bytes!("../tests/fonts/DejaVuSansMono.ttf");
+        FontFace::from_data(font_data, 0).expect("Failed to parse font")
+    })
+}
+
+/// A chain of the font in `data` followed by the [`embedded_font`], for tests drawing with
+/// fonts of their own.
+#[cfg(test)]
+pub fn chain_from_data(data: &'static [u8]) -> FontChain {
+    let face = FontFace::from_data(data, 0).expect("Failed to parse font");
+    Vec::leak(vec![&*Box::leak(Box::new(face)), embedded_font()])
+}
+
+/// The installed fonts best matching `config`: its family then its fallback families, all
+/// in its weight and style, followed by the [`embedded_font`].
+///
+/// Family names are matched case-insensitively, then the closest weight and style are
+/// picked among the faces of the family. The embedded font comes first instead when
+/// `family` is unset or not installed; fallback families that are not installed are skipped.
+pub fn resolve(config: &FontConfig) -> FontChain {
+    let mut chains = CHAINS.lock().expect("Font chain cache lock poisoned");
+
+    if let Some(chain) = chains.get(config) {
+        return chain;
+    }
+
+    let primary = config
+        .family
+        .as_deref()
+        .and_then(|family| face(family, config.weight, config.style))
+        .unwrap_or_else(embedded_font);
+    let fallbacks = config
+        .fallback
+        .iter()
+        .filter_map(|family| face(family, config.weight, config.style));
+
+    let mut chain: Vec<&'static FontFace> = Vec::new();
+
+    for font in [primary]
+        .into_iter()
+        .chain(fallbacks)
+        .chain([embedded_font()])
+    {
+        if !chain.iter().any(|known| std::ptr::eq(*known, font)) {
+            chain.push(font);
+        }
+    }
+
+    let chain: FontChain = Vec::leak(chain);
+    chains.insert(config.clone(), chain);
+    chain
+}
+
+/// The face of `family` closest to `weight` and `style`, loaded once.
+fn face(family: &str, weight: FontWeight, style: FontStyle) -> Option<&'static FontFace> {
+    let mut faces = FACES.lock().expect("Font cache lock poisoned");
+    let key = (family.to_owned(), weight, style);
+
+    if let Some(face) = faces.get(&key) {
+        return *face;
+    }
+
+    let face = load(family, weight, style).map(|face| &*Box::leak(Box::new(face)));
+
+    if face.is_none() {
+        eprintln!("Font `{family}` is not available");
+    }
+
+    faces.insert(key, face);
+    face
+}
+
+fn load(family: &str, weight: FontWeight, style: FontStyle) -> Option<FontFace> {
+    let database = &*SYSTEM_FONTS;
+
+    // The database matches family names exactly
+    let name = database
+        .faces()
+        .flat_map(|face| &face.families)
+        .map(|(name, _)| name)
+        .find(|name| name.eq_ignore_ascii_case(family))?;
+
+    let id = database.query(&Query {
+        families: &[Family::Name(name)],
+        weight: Weight(weight.0),
+        style: match style {
+            FontStyle::Normal => Style::Normal,
+            FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique => Style::Oblique,
+        },
+        ..Query::default()
+    })?;
+
+    // The face borrows its data, which is kept along with it
+    database
+        .with_face_data(id, |data, index| {
+            FontFace::from_data(Vec::leak(data.to_vec()), index)
+        })
+        .flatten()
+}
@@ -0,0 +1,534 @@
+use serde::Deserialize;
+
+use crate::components::BoundingBox;
+
+/// A rectangle in canvas pixels.
+///
+/// `x` and `y` may be negative when content overflows its container; the renderer clips
+/// whatever falls outside of the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The main axis along which a container places its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Children are placed left to right.
+    #[default]
+    Row,
+    /// Children are placed top to bottom.
+    Column,
+}
+
+/// Placement of content inside the space available along one axis.
+///
+/// When content is larger than the space, `Center` overflows evenly on both sides and `End`
+/// overflows on the start side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+/// Empty space kept around a node, outside of its size.
+///
+/// Also used as the `margin` of the bar in the configuration, where every side is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Margin {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+/// How a node sizes and places itself inside its parent container.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Style {
+    pub margin: Margin,
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Share of the free main-axis space this node takes, relative to its siblings.
+    ///
+    /// `0.0` keeps the node at its natural size.
+    pub flex_grow: f32,
+    /// Overrides the cross-axis alignment of the parent container for this node.
+    pub align_self: Option<Align>,
+}
+
+impl Style {
+    /// Clamps a `(width, height)` size to the min/max constraints; max wins over min.
+    fn clamp(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        let clamp = |value: u32, min: Option<u32>, max: Option<u32>| {
+            let value = min.map_or(value, |min| value.max(min));
+            max.map_or(value, |max| value.min(max))
+        };
+
+        (
+            clamp(width, self.min_width, self.max_width),
+            clamp(height, self.min_height, self.max_height),
+        )
+    }
+}
+
+pub enum NodeKind {
+    /// Content of a fixed size, e.g. a rendered component.
+    Leaf(BoundingBox),
+    /// A flexbox-like container laying its children out along `direction`.
+    Container {
+        direction: Direction,
+        /// Space between two consecutive children along the main axis.
+        spacing: u32,
+        /// Placement of the children along the main axis.
+        justify: Align,
+        /// Placement of each child along the cross axis.
+        align_items: Align,
+        children: Vec<Node>,
+    },
+}
+
+/// A node of the layout tree.
+pub struct Node {
+    pub style: Style,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    pub fn leaf(size: BoundingBox) -> Self {
+        Self {
+            style: Style::default(),
+            kind: NodeKind::Leaf(size),
+        }
+    }
+
+    pub fn container(direction: Direction, children: Vec<Node>) -> Self {
+        Self {
+            style: Style::default(),
+            kind: NodeKind::Container {
+                direction,
+                spacing: 0,
+                justify: Align::Start,
+                align_items: Align::Start,
+                children,
+            },
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Sets the spacing between children; no effect on leaves.
+    #[cfg(test)]
+    pub fn with_spacing(mut self, value: u32) -> Self {
+        if let NodeKind::Container { spacing, .. } = &mut self.kind {
+            *spacing = value;
+        }
+        self
+    }
+
+    /// Sets the main-axis placement of the children; no effect on leaves.
+    pub fn with_justify(mut self, value: Align) -> Self {
+        if let NodeKind::Container { justify, .. } = &mut self.kind {
+            *justify = value;
+        }
+        self
+    }
+
+    /// Sets the cross-axis placement of the children; no effect on leaves.
+    pub fn with_align_items(mut self, value: Align) -> Self {
+        if let NodeKind::Container { align_items, .. } = &mut self.kind {
+            *align_items = value;
+        }
+        self
+    }
+
+    /// Computes the rectangle of every leaf when this node fills `rect`.
+    ///
+    /// Rectangles are returned in depth-first order, which is the order the leaves were
+    /// added to the tree.
+    pub fn layout(&self, rect: Rect) -> Vec<Rect> {
+        let mut leaves = Vec::new();
+        self.layout_into(rect, &mut leaves);
+        leaves
+    }
+
+    /// The natural `(width, height)` of the node, margins excluded.
+    fn size(&self) -> (u32, u32) {
+        let size = match &self.kind {
+            NodeKind::Leaf(bbox) => (bbox.width, bbox.height),
+            NodeKind::Container {
+                direction,
+                spacing,
+                children,
+                ..
+            } => {
+                let (main, cross) = children
+                    .iter()
+                    .map(|child| direction.split(child.outer_size()))
+                    .fold((0, 0), |(main, cross), (child_main, child_cross)| {
+                        (main + child_main, cross.max(child_cross))
+                    });
+
+                let gaps = spacing * children.len().saturating_sub(1) as u32;
+                direction.join(main + gaps, cross)
+            }
+        };
+
+        self.style.clamp(size)
+    }
+
+    /// The natural `(width, height)` of the node, margins included.
+    fn outer_size(&self) -> (u32, u32) {
+        let (width, height) = self.size();
+        let margin = self.style.margin;
+
+        (
+            width + margin.left + margin.right,
+            height + margin.top + margin.bottom,
+        )
+    }
+
+    fn layout_into(&self, rect: Rect, leaves: &mut Vec<Rect>) {
+        let NodeKind::Container {
+            direction,
+            spacing,
+            justify,
+            align_items,
+            children,
+        } = &self.kind
+        else {
+            leaves.push(rect);
+            return;
+        };
+
+        let direction = *direction;
+        let (rect_main, rect_cross) = direction.split((rect.width, rect.height));
+        let (origin_main, origin_cross) = direction.split_position(rect);
+
+        // Outer main size of every child, grown below when there is space left
+        let mut child_main: Vec<u32> = children
+            .iter()
+            .map(|child| direction.split(child.outer_size()).0)
+            .collect();
+
+        let gaps = i64::from(*spacing) * children.len().saturating_sub(1) as i64;
+        let mut free =
+            i64::from(rect_main) - child_main.iter().map(|&m| i64::from(m)).sum::<i64>() - gaps;
+
+        // Free space is shared among the children that grow, in proportion to their
+        // `flex_grow`. A child reaching its max size stops growing and the space it leaves
+        // is shared again among the others.
+        let mut growing: Vec<usize> = (0..children.len())
+            .filter(|&i| children[i].style.flex_grow > 0.0)
+            .collect();
+
+        while free > 0 && !growing.is_empty() {
+            let available = free as f32;
+            let total_grow: f32 = growing.iter().map(|&i| children[i].style.flex_grow).sum();
+            let mut capped = false;
+
+            growing.retain(|&i| {
+                let child = &children[i];
+                let share = (available * child.style.flex_grow / total_grow) as i64;
+                let room = direction.split_max(&child.style).map_or(i64::MAX, |max| {
+                    i64::from(max) - i64::from(direction.split(child.size()).0)
+                });
+                let grown =
+                    i64::from(child_main[i]) - i64::from(direction.split(child.outer_size()).0);
+                let grow = share.min(room - grown).max(0);
+
+                child_main[i] += grow as u32;
+                free -= grow;
+
+                let keeps_growing = grown + grow < room;
+                capped |= !keeps_growing;
+                keeps_growing
+            });
+
+            // Shares are rounded down, the remainder is left unused
+            if !capped {
+                break;
+            }
+        }
+
+        let mut cursor = i64::from(origin_main)
+            + match justify {
+                Align::Start => 0,
+                Align::Center => free / 2,
+                Align::End => free,
+            };
+
+        for (child, outer_main) in children.iter().zip(child_main) {
+            let (margin_before, margin_after, cross_before, cross_after) =
+                direction.split_margin(child.style.margin);
+
+            let inner_main = outer_main.saturating_sub(margin_before + margin_after);
+            let inner_cross = direction.split(child.size()).1;
+
+            let cross_space =
+                i64::from(rect_cross) - i64::from(cross_before) - i64::from(cross_after);
+            let cross_offset = match child.style.align_self.unwrap_or(*align_items) {
+                Align::Start => 0,
+                Align::Center => (cross_space - i64::from(inner_cross)) / 2,
+                Align::End => cross_space - i64::from(inner_cross),
+            };
+
+            let main_position = cursor + i64::from(margin_before);
+            let cross_position = i64::from(origin_cross) + i64::from(cross_before) + cross_offset;
+
+            let (width, height) = direction.join(inner_main, inner_cross);
+            let (x, y) = direction.join_position(main_position, cross_position);
+
+            child.layout_into(
+                Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                },
+                leaves,
+            );
+
+            cursor += i64::from(outer_main) + i64::from(*spacing);
+        }
+    }
+}
+
+impl Direction {
+    /// Splits a `(width, height)` pair into `(main, cross)`.
+    pub fn split(self, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Self::Row => (width, height),
+            Self::Column => (height, width),
+        }
+    }
+
+    /// Joins a `(main, cross)` pair back into `(width, height)`.
+    pub fn join(self, main: u32, cross: u32) -> (u32, u32) {
+        self.split((main, cross))
+    }
+
+    /// Splits the position of `rect` into `(main, cross)`.
+    pub fn split_position(self, rect: Rect) -> (i32, i32) {
+        match self {
+            Self::Row => (rect.x, rect.y),
+            Self::Column => (rect.y, rect.x),
+        }
+    }
+
+    /// Joins a `(main, cross)` position back into `(x, y)`.
+    pub fn join_position(self, main: i64, cross: i64) -> (i32, i32) {
+        let (x, y) = match self {
+            Self::Row => (main, cross),
+            Self::Column => (cross, main),
+        };
+
+        (x as i32, y as i32)
+    }
+
+    /// The rectangle spanning `[start, end)` along the main axis and `cross` pixels across.
+    pub fn band(self, start: u32, end: u32, cross: u32) -> Rect {
+        let (x, y) = self.join_position(i64::from(start), 0);
+        let (width, height) = self.join(end - start, cross);
+
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The maximum size of a node along the main axis.
+    fn split_max(self, style: &Style) -> Option<u32> {
+        match self {
+            Self::Row => style.max_width,
+            Self::Column => style.max_height,
+        }
+    }
+
+    /// Splits a margin into `(main_before, main_after, cross_before, cross_after)`.
+    pub fn split_margin(self, margin: Margin) -> (u32, u32, u32, u32) {
+        match self {
+            Self::Row => (margin.left, margin.right, margin.top, margin.bottom),
+            Self::Column => (margin.top, margin.bottom, margin.left, margin.right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(width: u32, height: u32) -> Node {
+        Node::leaf(BoundingBox { width, height })
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn grow(flex_grow: f32) -> Style {
+        Style {
+            flex_grow,
+            ..Style::default()
+        }
+    }
+
+    #[test]
+    fn spacing_separates_children() {
+        let row = Node::container(Direction::Row, vec![leaf(10, 5), leaf(20, 5), leaf(5, 5)])
+            .with_spacing(4);
+
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 5)),
+            vec![rect(0, 0, 10, 5), rect(14, 0, 20, 5), rect(38, 0, 5, 5)]
+        );
+    }
+
+    #[test]
+    fn spacing_counts_in_container_size() {
+        let column =
+            Node::container(Direction::Column, vec![leaf(10, 5), leaf(8, 5)]).with_spacing(3);
+
+        assert_eq!(column.size(), (10, 13));
+    }
+
+    #[test]
+    fn grow_shares_free_space() {
+        let row = Node::container(
+            Direction::Row,
+            vec![
+                leaf(10, 5).with_style(grow(1.0)),
+                leaf(10, 5),
+                leaf(10, 5).with_style(grow(3.0)),
+            ],
+        );
+
+        // 70 free pixels, a quarter to the first child and three to the last
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 5)),
+            vec![rect(0, 0, 27, 5), rect(27, 0, 10, 5), rect(37, 0, 62, 5)]
+        );
+    }
+
+    #[test]
+    fn grow_stops_at_max_width() {
+        let capped = Style {
+            max_width: Some(20),
+            ..grow(1.0)
+        };
+        let row = Node::container(
+            Direction::Row,
+            vec![
+                leaf(10, 5).with_style(capped),
+                leaf(10, 5).with_style(grow(1.0)),
+            ],
+        );
+
+        // The space the first child cannot take goes to the second
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 5)),
+            vec![rect(0, 0, 20, 5), rect(20, 0, 80, 5)]
+        );
+    }
+
+    #[test]
+    fn min_and_max_clamp_natural_size() {
+        let style = Style {
+            min_width: Some(15),
+            max_height: Some(4),
+            ..Style::default()
+        };
+
+        assert_eq!(leaf(10, 5).with_style(style).size(), (15, 4));
+        // Max wins over min
+        let style = Style {
+            min_width: Some(30),
+            max_width: Some(20),
+            ..Style::default()
+        };
+        assert_eq!(leaf(10, 5).with_style(style).size(), (20, 5));
+    }
+
+    #[test]
+    fn margins_surround_children() {
+        let margin = Style {
+            margin: Margin {
+                top: 1,
+                right: 2,
+                bottom: 0,
+                left: 3,
+            },
+            ..Style::default()
+        };
+        let row = Node::container(
+            Direction::Row,
+            vec![leaf(10, 5).with_style(margin), leaf(10, 5)],
+        );
+
+        assert_eq!(row.size(), (25, 6));
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 6)),
+            vec![rect(3, 1, 10, 5), rect(15, 0, 10, 5)]
+        );
+    }
+
+    #[test]
+    fn cross_axis_alignment() {
+        let row = Node::container(
+            Direction::Row,
+            vec![
+                leaf(10, 4),
+                leaf(10, 4).with_style(Style {
+                    align_self: Some(Align::End),
+                    ..Style::default()
+                }),
+            ],
+        )
+        .with_align_items(Align::Center);
+
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 10)),
+            vec![rect(0, 3, 10, 4), rect(10, 6, 10, 4)]
+        );
+    }
+
+    #[test]
+    fn center_overflows_on_both_sides() {
+        let row = Node::container(Direction::Row, vec![leaf(60, 5), leaf(60, 5)])
+            .with_justify(Align::Center);
+
+        assert_eq!(
+            row.layout(rect(0, 0, 100, 5)),
+            vec![rect(-10, 0, 60, 5), rect(50, 0, 60, 5)]
+        );
+    }
+
+    #[test]
+    fn end_overflows_on_the_start_side() {
+        let column = Node::container(Direction::Column, vec![leaf(5, 60), leaf(5, 60)])
+            .with_justify(Align::End);
+
+        assert_eq!(
+            column.layout(rect(0, 0, 5, 100)),
+            vec![rect(0, -20, 5, 60), rect(0, 40, 5, 60)]
+        );
+    }
+}
@@ -1,55 +1,256 @@
+mod cli;
 mod components;
 mod configuration;
+mod error;
+mod fonts;
 mod layout;
+mod modules;
+mod renderer;
 mod simbar;
+#[cfg(test)]
+mod snapshots;
 mod widgets;
 
-use configuration::SIMBAR_CONFIG;
-use simbar::SimBar;
+use std::{
+    io,
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cli::CliArgs;
+use configuration::{Config, ConfigWatcher, default_config_path, load_config};
+use error::SimbarError;
+use modules::{ModuleSources, build_modules};
+use simbar::{DrawSize, FractionalScaleState, SimBar};
 use smithay_client_toolkit::{
-    compositor::CompositorState, output::OutputState, registry::RegistryState, seat::SeatState,
-    shell::wlr_layer::LayerShell, shm::Shm,
+    compositor::CompositorState,
+    output::OutputState,
+    reexports::{
+        calloop::{EventLoop, Interest, LoopHandle, Mode, PostAction, generic::Generic},
+        calloop_wayland_source::WaylandSource,
+    },
+    registry::RegistryState,
+    seat::SeatState,
+    shell::wlr_layer::LayerShell,
+    shm::Shm,
 };
-use wayland_client::{Connection, globals::registry_queue_init};
+use wayland_client::{
+    Connection, QueueHandle,
+    globals::{BindError, registry_queue_init},
+};
+use widgets::{SimbarWidgets, WidgetTrees};
+
+/// The first delay before reconnecting in `--supervise` mode, doubled after each failure.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+/// The longest delay between two connection attempts in `--supervise` mode.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
 
-fn main() {
+fn main() -> ExitCode {
     env_logger::init();
 
-    assert_ne!(SIMBAR_CONFIG.width_fallback, 0);
-    assert_ne!(SIMBAR_CONFIG.width, Some(0));
-    assert_ne!(SIMBAR_CONFIG.height, 0);
+    let args = match CliArgs::parse() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let conn = Connection::connect_to_env().unwrap();
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Loads the configuration and runs the bar until it exits, or renders it once with
+/// `--render-png`.
+fn run(args: CliArgs) -> Result<(), SimbarError> {
+    let config = load_config(args.config.as_deref())?;
+
+    if let Some(path) = args.render_png {
+        let size = args
+            .size
+            .unwrap_or_else(|| config.simbar.fallback_size().into());
+
+        return render_png(&config, size, &path);
+    }
 
-    let (globals, mut event_queue) = registry_queue_init(&conn).unwrap();
+    if args.supervise {
+        supervise(args.config, config)
+    } else {
+        run_bar(args.config, config)
+    }
+}
+
+/// Runs the bar like [`run_bar`], connecting again with an exponential backoff whenever the
+/// compositor is gone, e.g. while it restarts.
+///
+/// Every attempt starts from scratch: the configuration is read again, falling back to the
+/// last valid one, and all monitors, widgets and modules are rebuilt on the new connection.
+fn supervise(config_path: Option<PathBuf>, mut config: Config) -> Result<(), SimbarError> {
+    let mut delay = MIN_RECONNECT_DELAY;
+
+    loop {
+        let started = Instant::now();
+
+        match run_bar(config_path.clone(), config.clone()) {
+            Err(err) if err.is_disconnect() => {
+                // A bar that ran for a while lost a working compositor, retry quickly
+                if started.elapsed() > MAX_RECONNECT_DELAY {
+                    delay = MIN_RECONNECT_DELAY;
+                }
+
+                eprintln!("{err}, reconnecting in {:.1}s", delay.as_secs_f32());
+                thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+            result => return result,
+        }
+
+        match load_config(config_path.as_deref()) {
+            Ok(new_config) => config = new_config,
+            Err(err) => eprintln!("Invalid configuration, keeping the previous one: {err}"),
+        }
+    }
+}
+
+/// Connects to the compositor and runs the bar until it exits or the connection is lost.
+fn run_bar(config_path: Option<PathBuf>, config: Config) -> Result<(), SimbarError> {
+    let conn = Connection::connect_to_env()?;
+
+    let (globals, event_queue) = registry_queue_init(&conn)?;
 
     let qh = event_queue.handle();
 
-    let compositor = CompositorState::bind(&globals, &qh).expect("wl_compositor is not available");
+    let compositor = CompositorState::bind(&globals, &qh).map_err(missing("wl_compositor"))?;
+
+    let layer_shell = LayerShell::bind(&globals, &qh).map_err(missing("zwlr_layer_shell_v1"))?;
+
+    let shm = Shm::bind(&globals, &qh).map_err(missing("wl_shm"))?;
+
+    let fractional_scale = match FractionalScaleState::bind(&globals, &qh) {
+        Ok(state) => Some(state),
+        Err(err) => {
+            eprintln!("Fractional scaling is not available, using integer scaling: {err}");
+            None
+        }
+    };
+
+    let watcher = config_path
+        .clone()
+        .or_else(default_config_path)
+        .and_then(|path| match ConfigWatcher::new(&path) {
+            Ok(watcher) => Some(watcher),
+            Err(err) => {
+                eprintln!(
+                    "Configuration changes will not be reloaded ({}): {err}",
+                    path.display()
+                );
+                None
+            }
+        });
+
+    let mut event_loop: EventLoop<SimBar> = EventLoop::try_new()?;
+    let handle = event_loop.handle();
 
-    let layer_shell = LayerShell::bind(&globals, &qh).expect("layer shell is not available");
+    WaylandSource::new(conn.clone(), event_queue)
+        .insert(handle.clone())
+        .map_err(|err| err.error)?;
 
-    let shm = Shm::bind(&globals, &qh).expect("wl_shm is not available");
+    if let Some(watcher) = watcher
+        && let Err(err) = watch_config(&handle, &qh, watcher)
+    {
+        eprintln!("Configuration changes will not be reloaded: {err}");
+    }
 
     let mut sim_bar = SimBar {
+        config,
+        config_path,
         registry_state: RegistryState::new(&globals),
         seat_state: SeatState::new(&globals, &qh),
         output_state: OutputState::new(&globals, &qh),
         shm,
         compositor,
         layer_shell,
+        fractional_scale,
+        module_sources: ModuleSources::new(handle, qh.clone()),
         monitors: Vec::new(),
         pointer: None,
         exit: false,
-        last_frame_time: 0,
     };
 
-    loop {
-        event_queue.blocking_dispatch(&mut sim_bar).unwrap();
+    let signal = event_loop.get_signal();
+
+    // Only the Wayland source can fail once running, when the connection breaks
+    event_loop
+        .run(None, &mut sim_bar, |sim_bar| {
+            if sim_bar.exit {
+                println!("exiting example");
+                signal.stop();
+            }
+        })
+        .map_err(SimbarError::ConnectionLost)?;
 
-        if sim_bar.exit {
-            println!("exiting example");
-            break;
+    Ok(())
+}
+
+/// Maps the failure to bind the global `interface` to a [`SimbarError`].
+fn missing(interface: &'static str) -> impl FnOnce(BindError) -> SimbarError {
+    move |source| SimbarError::MissingGlobal { interface, source }
+}
+
+/// Renders the bar once at `size`, without a compositor, and writes it to `path`.
+///
+/// `[[output]]` sections are not applied since there is no output to match.
+fn render_png(config: &Config, size: DrawSize, path: &Path) -> Result<(), SimbarError> {
+    let widgets = SimbarWidgets::from_config(config);
+    let trees: WidgetTrees = [&widgets].into_iter().collect();
+
+    for module in build_modules(&config.widgets) {
+        if let Err(err) = module.start(&trees, &mut ModuleSources::headless()) {
+            eprintln!("Failed to start module: {err}");
         }
     }
+
+    let frame = renderer::render(&widgets, size, 1.0, config.theme.background_color);
+
+    frame
+        .write_png(path)
+        .map_err(|source| SimbarError::WritePng {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Registers `watcher` on the event loop, reloading the configuration whenever it reports a
+/// change.
+fn watch_config(
+    handle: &LoopHandle<'static, SimBar>,
+    qh: &QueueHandle<SimBar>,
+    mut watcher: ConfigWatcher,
+) -> io::Result<()> {
+    // The source only polls a duplicate of the descriptor, the callback owns the watcher
+    let fd = watcher.as_fd().try_clone_to_owned()?;
+    let qh = qh.clone();
+
+    handle
+        .insert_source(
+            Generic::new(fd, Interest::READ, Mode::Level),
+            move |_, _, sim_bar| {
+                if watcher.changed() {
+                    sim_bar.reload_config(&qh);
+                }
+
+                Ok(PostAction::Continue)
+            },
+        )
+        .map_err(|err| io::Error::from(err.error))?;
+
+    Ok(())
 }
@@ -0,0 +1,124 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use chrono::{
+    DateTime, Local, Timelike, Utc,
+    format::{Fixed, Item, Numeric, StrftimeItems},
+};
+use chrono_tz::Tz;
+
+use crate::{components::Label, widgets::WidgetTrees};
+
+use super::{Module, ModuleSources, Tick};
+
+/// Shows the current time in a label, in one or several time zones.
+///
+/// The label is refreshed right on the next second boundary when the format displays
+/// seconds, and on the next minute boundary otherwise.
+pub struct Clock {
+    label_id: String,
+    format: String,
+    timezones: Vec<Tz>,
+    separator: String,
+    every_second: bool,
+    text: String,
+}
+
+impl Clock {
+    /// Creates a clock feeding the label registered as `label_id`.
+    ///
+    /// `format` must be a valid strftime format. An empty `timezones` shows the local time.
+    pub fn new(label_id: String, format: String, timezones: Vec<Tz>, separator: String) -> Self {
+        let every_second = StrftimeItems::new(&format).any(|item| {
+            matches!(
+                item,
+                Item::Numeric(
+                    Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp,
+                    _
+                ) | Item::Fixed(
+                    Fixed::Nanosecond
+                        | Fixed::Nanosecond3
+                        | Fixed::Nanosecond6
+                        | Fixed::Nanosecond9
+                        | Fixed::RFC2822
+                        | Fixed::RFC3339
+                        | Fixed::Internal(_)
+                )
+            )
+        });
+
+        Self {
+            label_id,
+            format,
+            timezones,
+            separator,
+            every_second,
+            text: String::new(),
+        }
+    }
+
+    /// Refreshes the label and returns whether its text changed, along with the instant of
+    /// the next refresh.
+    fn update(&mut self, widgets: &WidgetTrees) -> (bool, Instant) {
+        let now = Utc::now();
+        let next = Instant::now() + self.until_next_tick(now);
+
+        let text = self.format(now);
+        if text == self.text {
+            return (false, next);
+        }
+
+        self.text = text;
+        let changed = widgets.update::<Label>(&self.label_id, |label| label.update(&self.text));
+
+        (changed, next)
+    }
+
+    fn format(&self, now: DateTime<Utc>) -> String {
+        if self.timezones.is_empty() {
+            return now.with_timezone(&Local).format(&self.format).to_string();
+        }
+
+        self.timezones
+            .iter()
+            .map(|tz| now.with_timezone(tz).format(&self.format).to_string())
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+
+    /// Time left until the next second or minute boundary.
+    ///
+    /// Every time zone offset is a whole number of minutes, so the boundary is the same for
+    /// all of them.
+    fn until_next_tick(&self, now: DateTime<Utc>) -> Duration {
+        // Leap seconds are reported as nanoseconds above one second
+        let into_second = Duration::from_nanos(u64::from(now.nanosecond() % 1_000_000_000));
+
+        if self.every_second {
+            Duration::from_secs(1) - into_second
+        } else {
+            Duration::from_secs(60) - Duration::from_secs(u64::from(now.second())) - into_second
+        }
+    }
+}
+
+impl Module for Clock {
+    fn start(
+        mut self: Box<Self>,
+        widgets: &WidgetTrees,
+        sources: &mut ModuleSources,
+    ) -> io::Result<()> {
+        let (_, next) = self.update(widgets);
+
+        sources.add_timer(next, move |widgets| {
+            let (redraw, next) = self.update(widgets);
+
+            Tick {
+                redraw,
+                next: Some(next),
+            }
+        })
+    }
+}
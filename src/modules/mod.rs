@@ -0,0 +1,184 @@
+mod clock;
+
+use std::{io, os::fd::AsFd, time::Instant};
+
+use smithay_client_toolkit::reexports::calloop::{
+    Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+};
+use wayland_client::{QueueHandle, protocol::wl_output::WlOutput};
+
+use crate::{
+    configuration::{ComponentConfig, WidgetsConfig},
+    simbar::SimBar,
+    widgets::{Region, WidgetTrees, component_key},
+};
+
+pub use clock::Clock;
+
+/// A source of content for the bar, feeding one or more components of every monitor's
+/// widget tree.
+///
+/// A module is started once per configuration: it fills its components, then registers the
+/// timers and file descriptors that keep them up to date on the bar's event loop. The bar is
+/// redrawn only when one of those sources reports a change, so a module waiting on nothing
+/// costs no wakeup at all.
+pub trait Module {
+    /// Fills the components fed by this module and registers its event sources.
+    fn start(self: Box<Self>, widgets: &WidgetTrees, sources: &mut ModuleSources)
+    -> io::Result<()>;
+}
+
+/// The outcome of a module timer firing.
+pub struct Tick {
+    /// Whether anything visible changed, i.e. whether the bar needs a redraw.
+    pub redraw: bool,
+    /// When the timer should fire next, `None` to stop it.
+    pub next: Option<Instant>,
+}
+
+/// The event sources registered by the running modules on the bar's event loop.
+///
+/// Sources are removed all at once by [`ModuleSources::clear`] when the configuration is
+/// reloaded or the monitors change, and the modules are started again.
+pub struct ModuleSources {
+    /// `None` when rendering without a compositor, see [`ModuleSources::headless`].
+    event_loop: Option<(LoopHandle<'static, SimBar>, QueueHandle<SimBar>)>,
+    tokens: Vec<RegistrationToken>,
+    outputs: Vec<WlOutput>,
+}
+
+impl ModuleSources {
+    pub fn new(handle: LoopHandle<'static, SimBar>, qh: QueueHandle<SimBar>) -> Self {
+        Self {
+            event_loop: Some((handle, qh)),
+            tokens: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Sources that are never run, for a single headless render.
+    ///
+    /// Modules still fill their components when started, which is all such a render needs.
+    pub fn headless() -> Self {
+        Self {
+            event_loop: None,
+            tokens: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Sets the outputs whose widget trees are passed to the sources registered next.
+    ///
+    /// Monitors with different widget configurations run their own modules.
+    pub fn set_outputs(&mut self, outputs: Vec<WlOutput>) {
+        self.outputs = outputs;
+    }
+
+    /// Calls `callback` at `deadline`, then at every instant it schedules until it returns a
+    /// [`Tick`] without `next`.
+    pub fn add_timer<F>(&mut self, deadline: Instant, mut callback: F) -> io::Result<()>
+    where
+        F: FnMut(&WidgetTrees) -> Tick + 'static,
+    {
+        let Some((handle, qh)) = &self.event_loop else {
+            return Ok(());
+        };
+        let qh = qh.clone();
+        let outputs = self.outputs.clone();
+
+        let token = handle
+            .insert_source(Timer::from_deadline(deadline), move |_, _, sim_bar| {
+                let tick = callback(&sim_bar.widget_trees(&outputs));
+
+                if tick.redraw {
+                    sim_bar.redraw(&qh);
+                }
+
+                match tick.next {
+                    Some(next) => TimeoutAction::ToInstant(next),
+                    None => TimeoutAction::Drop,
+                }
+            })
+            .map_err(|err| io::Error::from(err.error))?;
+
+        self.tokens.push(token);
+        Ok(())
+    }
+
+    /// Calls `callback` whenever `fd` is readable, e.g. a pipe from a child process or a
+    /// socket.
+    ///
+    /// `callback` returns whether anything visible changed. It must consume the pending data,
+    /// otherwise it is called again right away.
+    #[allow(unused)]
+    pub fn add_fd<F, C>(&mut self, fd: F, mut callback: C) -> io::Result<()>
+    where
+        F: AsFd + 'static,
+        C: FnMut(&F, &WidgetTrees) -> bool + 'static,
+    {
+        let Some((handle, qh)) = &self.event_loop else {
+            return Ok(());
+        };
+        let qh = qh.clone();
+        let outputs = self.outputs.clone();
+
+        let token = handle
+            .insert_source(
+                Generic::new(fd, Interest::READ, Mode::Level),
+                move |_, fd, sim_bar| {
+                    if callback(fd, &sim_bar.widget_trees(&outputs)) {
+                        sim_bar.redraw(&qh);
+                    }
+
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|err| io::Error::from(err.error))?;
+
+        self.tokens.push(token);
+        Ok(())
+    }
+
+    /// Removes every registered source.
+    pub fn clear(&mut self) {
+        for token in self.tokens.drain(..) {
+            if let Some((handle, _)) = &self.event_loop {
+                handle.remove(token);
+            }
+        }
+    }
+}
+
+/// Creates the modules driving the components of the widget configuration.
+pub fn build_modules(config: &WidgetsConfig) -> Vec<Box<dyn Module>> {
+    let regions = [
+        (Region::Left, &config.left),
+        (Region::Center, &config.center),
+        (Region::Right, &config.right),
+    ];
+
+    let mut modules: Vec<Box<dyn Module>> = Vec::new();
+
+    for (region, components) in regions {
+        for (index, component) in components.iter().enumerate() {
+            if let ComponentConfig::Clock {
+                format,
+                timezones,
+                separator,
+                ..
+            } = component
+            {
+                modules.push(Box::new(Clock::new(
+                    component_key(region, index, component),
+                    format.clone(),
+                    timezones.clone(),
+                    separator.clone(),
+                )));
+            }
+        }
+    }
+
+    modules
+}
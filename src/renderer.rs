@@ -0,0 +1,89 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use crate::{
+    components::ArgbColor,
+    layout::Rect,
+    simbar::DrawSize,
+    widgets::{Placement, Rendered, SimbarWidgets},
+};
+
+/// A fully rendered bar, independent of any Wayland surface.
+pub struct Frame {
+    /// The dimensions of the frame in physical pixels.
+    pub size: DrawSize,
+    /// ARGB8888 pixels, row by row, with the background already blended in.
+    pub pixels: Vec<u32>,
+    /// Where every component of the widget tree landed, see [`SimbarWidgets::render`].
+    pub placements: Vec<Placement>,
+}
+
+/// Renders `widgets` over `background` into a frame of `size` physical pixels, at `scale`
+/// physical pixels per logical pixel.
+pub fn render(widgets: &SimbarWidgets, size: DrawSize, scale: f32, background: ArgbColor) -> Frame {
+    Frame::new(widgets.render(size, scale), size, background)
+}
+
+impl Frame {
+    /// Blends the pixels of a canvas of `size` rendered by a widget tree over `background`.
+    pub fn new(rendered: Rendered, size: DrawSize, background: ArgbColor) -> Self {
+        let pixels = rendered
+            .pixels
+            .iter()
+            .map(|pixel| match pixel {
+                Some(fg_pixel) => fg_pixel.blend_with_background(background),
+                None => background,
+            })
+            .map(u32::from)
+            .collect();
+
+        Self {
+            size,
+            pixels,
+            placements: rendered.placements,
+        }
+    }
+
+    /// Copies the pixels inside `band` into `canvas`, a buffer of the same size in the
+    /// little-endian byte order of `wl_shm` ARGB8888.
+    pub fn copy_band(&self, band: Rect, canvas: &mut [u8]) {
+        let width = self.size.width as usize;
+        let (x0, y0) = (band.x.max(0) as usize, band.y.max(0) as usize);
+        let x1 = (x0 + band.width as usize).min(width);
+        let y1 = (y0 + band.height as usize).min(self.size.height as usize);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let i = y * width + x;
+                canvas[i * 4..i * 4 + 4].copy_from_slice(&self.pixels[i].to_le_bytes());
+            }
+        }
+    }
+
+    /// Writes the frame to `path` as an RGBA PNG.
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+
+        let mut encoder = png::Encoder::new(file, self.size.width, self.size.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let data: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| {
+                let [blue, green, red, alpha] = pixel.to_le_bytes();
+                [red, green, blue, alpha]
+            })
+            .collect();
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+
+        Ok(())
+    }
+}
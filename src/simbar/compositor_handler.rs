@@ -1,6 +1,4 @@
-use smithay_client_toolkit::{
-    compositor::CompositorHandler, delegate_compositor, shell::WaylandSurface,
-};
+use smithay_client_toolkit::{compositor::CompositorHandler, delegate_compositor};
 use wayland_client::{
     Connection, QueueHandle,
     protocol::{
@@ -9,10 +7,7 @@ use wayland_client::{
     },
 };
 
-use super::SimBar;
-use crate::configuration::SIMBAR_CONFIG;
-
-const MIN_FRAME_TIME_MS: u32 = 1_000 / SIMBAR_CONFIG.frame_rate;
+use super::{SimBar, SurfaceScale};
 
 delegate_compositor!(SimBar);
 
@@ -20,11 +15,13 @@ impl CompositorHandler for SimBar {
     fn scale_factor_changed(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _surface: &WlSurface,
-        _new_factor: i32,
+        qh: &QueueHandle<Self>,
+        surface: &WlSurface,
+        new_factor: i32,
     ) {
-        println!("scale_factor_changed");
+        println!("scale_factor_changed: {new_factor}");
+
+        self.set_scale(qh, surface, SurfaceScale::Integer(new_factor));
     }
 
     fn transform_changed(
@@ -42,29 +39,9 @@ impl CompositorHandler for SimBar {
         _conn: &Connection,
         qh: &QueueHandle<Self>,
         surface: &WlSurface,
-        time: u32,
+        _time: u32,
     ) {
-        surface.frame(qh, surface.clone());
-
-        let allow_draw = self
-            .monitors
-            .iter()
-            .find(|m| m.layer_surface.wl_surface() == surface)
-            .map(|m| m.is_primary)
-            .unwrap_or(false);
-
-        if allow_draw && time.wrapping_sub(self.last_frame_time) >= MIN_FRAME_TIME_MS {
-            self.draw(qh, surface);
-            self.last_frame_time = time;
-        }
-
-        if let Some(monitor) = self
-            .monitors
-            .iter_mut()
-            .find(|m| m.layer_surface.wl_surface() == surface)
-        {
-            monitor.layer_surface.commit();
-        }
+        self.frame_done(qh, surface);
     }
 
     fn surface_enter(
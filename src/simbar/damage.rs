@@ -0,0 +1,54 @@
+use crate::{
+    layout::{Direction, Rect},
+    widgets::Placement,
+};
+
+/// Returns the spans `[start, end)` along `direction` of a canvas `length` pixels long on
+/// that axis that must be repainted, sorted and without overlaps.
+///
+/// `previous` holds the component rectangles of the last committed frame, or `None` when the
+/// buffer does not hold a frame yet, in which case the whole canvas is damaged. Otherwise a
+/// component is damaged where it was and where it is now whenever it is dirty or moved.
+///
+/// Damage always covers the whole thickness of the bar: regions occlude each other by spans
+/// along the bar (see [`crate::widgets::composite`]), so a moving component can reveal or
+/// hide another region anywhere in the span it touches.
+pub fn damaged_spans(
+    direction: Direction,
+    length: u32,
+    previous: Option<&[Rect]>,
+    placements: &[Placement],
+) -> Vec<(u32, u32)> {
+    let previous = match previous {
+        Some(previous) if previous.len() == placements.len() => previous,
+        // First frame, or the widget tree was rebuilt
+        _ => return vec![(0, length)],
+    };
+
+    let mut spans: Vec<(u32, u32)> = placements
+        .iter()
+        .zip(previous)
+        .filter(|(placement, old)| placement.dirty || placement.rect != **old)
+        .flat_map(|(placement, old)| [placement.rect, *old])
+        .filter_map(|rect| {
+            let position = i64::from(direction.split_position(rect).0);
+            let size = i64::from(direction.split((rect.width, rect.height)).0);
+
+            let start = position.clamp(0, i64::from(length)) as u32;
+            let end = (position + size).clamp(0, i64::from(length)) as u32;
+            (start < end).then_some((start, end))
+        })
+        .collect();
+
+    spans.sort_unstable();
+
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
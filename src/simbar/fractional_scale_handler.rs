@@ -0,0 +1,79 @@
+use smithay_client_toolkit::reexports::protocols::wp::{
+    fractional_scale::v1::client::{
+        wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1,
+        wp_fractional_scale_v1::{self, WpFractionalScaleV1},
+    },
+    viewporter::client::{wp_viewport::WpViewport, wp_viewporter::WpViewporter},
+};
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, delegate_noop,
+    globals::{BindError, GlobalList},
+    protocol::wl_surface::WlSurface,
+};
+
+use super::{SimBar, SurfaceScale};
+
+/// The `wp_fractional_scale_manager_v1` and `wp_viewporter` globals.
+///
+/// Both are needed: the compositor reports the preferred scale through the former, and the
+/// latter maps the physical buffer back onto the logical surface size.
+pub struct FractionalScaleState {
+    manager: WpFractionalScaleManagerV1,
+    viewporter: WpViewporter,
+}
+
+/// The per-surface objects of [`FractionalScaleState`].
+pub struct FractionalSurface {
+    pub fractional_scale: WpFractionalScaleV1,
+    pub viewport: WpViewport,
+}
+
+impl FractionalScaleState {
+    pub fn bind(globals: &GlobalList, qh: &QueueHandle<SimBar>) -> Result<Self, BindError> {
+        let manager = globals.bind(qh, 1..=1, ())?;
+        let viewporter = globals.bind(qh, 1..=1, ())?;
+
+        Ok(Self {
+            manager,
+            viewporter,
+        })
+    }
+
+    /// Starts tracking the preferred scale of `surface`, see [`SimBar::set_scale`].
+    pub fn get_surface(&self, surface: &WlSurface, qh: &QueueHandle<SimBar>) -> FractionalSurface {
+        FractionalSurface {
+            fractional_scale: self
+                .manager
+                .get_fractional_scale(surface, qh, surface.clone()),
+            viewport: self.viewporter.get_viewport(surface, qh, ()),
+        }
+    }
+}
+
+impl Drop for FractionalSurface {
+    fn drop(&mut self) {
+        self.fractional_scale.destroy();
+        self.viewport.destroy();
+    }
+}
+
+delegate_noop!(SimBar: ignore WpFractionalScaleManagerV1);
+delegate_noop!(SimBar: ignore WpViewporter);
+delegate_noop!(SimBar: ignore WpViewport);
+
+impl Dispatch<WpFractionalScaleV1, WlSurface> for SimBar {
+    fn event(
+        state: &mut Self,
+        _proxy: &WpFractionalScaleV1,
+        event: wp_fractional_scale_v1::Event,
+        surface: &WlSurface,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wp_fractional_scale_v1::Event::PreferredScale { scale } = event {
+            println!("preferred_scale: {:.3}", f64::from(scale) / 120.0);
+
+            state.set_scale(qh, surface, SurfaceScale::Fractional(scale));
+        }
+    }
+}
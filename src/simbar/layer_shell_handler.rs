@@ -5,7 +5,7 @@ use smithay_client_toolkit::{
         wlr_layer::{LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
     },
 };
-use wayland_client::{Connection, QueueHandle, protocol::wl_shm::Format};
+use wayland_client::{Connection, QueueHandle};
 
 use super::SimBar;
 
@@ -21,7 +21,7 @@ impl LayerShellHandler for SimBar {
         _conn: &Connection,
         qh: &QueueHandle<Self>,
         layer: &LayerSurface,
-        _configure: LayerSurfaceConfigure,
+        configure: LayerSurfaceConfigure,
         _serial: u32,
     ) {
         if let Some(monitor) = self
@@ -31,30 +31,18 @@ impl LayerShellHandler for SimBar {
         {
             let surface = monitor.layer_surface.wl_surface().clone();
 
+            // The compositor picks the length of a bar stretched between two edges
+            if let (width @ 1.., height @ 1..) = configure.new_size {
+                monitor.resize((width, height).into());
+            }
+
             if monitor.buffer.is_none() {
                 println!("Create buffer and make init draw call");
 
-                let stride = monitor.draw_size.width * 4;
-
-                let (buffer, _) = monitor
-                    .pool
-                    .create_buffer(
-                        monitor.draw_size.width as i32,
-                        monitor.draw_size.height as i32,
-                        stride as i32,
-                        Format::Argb8888,
-                    )
-                    .expect("Failed to create new buffer.");
-
-                buffer
-                    .attach_to(monitor.layer_surface.wl_surface())
-                    .expect("Failed to attach buffer");
-
-                monitor.layer_surface.commit();
-
-                monitor.buffer = Some(buffer);
-
-                self.draw(qh, &surface);
+                match monitor.attach_buffer() {
+                    Ok(()) => self.draw(qh, &surface),
+                    Err(err) => eprintln!("Cannot show the bar on this output: {err}"),
+                }
             }
         }
     }
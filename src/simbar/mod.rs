@@ -1,4 +1,6 @@
 mod compositor_handler;
+mod damage;
+mod fractional_scale_handler;
 mod layer_shell_handler;
 mod mouse_handler;
 mod output_handler;
@@ -6,14 +8,18 @@ mod registry_handler;
 mod seat_handler;
 mod shm_handler;
 
+use std::path::PathBuf;
+
+use damage::damaged_spans;
+pub use fractional_scale_handler::{FractionalScaleState, FractionalSurface};
 use smithay_client_toolkit::{
     compositor::CompositorState,
-    output::OutputState,
+    output::{OutputInfo, OutputState},
     registry::RegistryState,
     seat::SeatState,
     shell::{
         WaylandSurface,
-        wlr_layer::{LayerShell, LayerSurface},
+        wlr_layer::{Anchor, Layer, LayerShell, LayerSurface},
     },
     shm::{
         Shm,
@@ -22,13 +28,16 @@ use smithay_client_toolkit::{
 };
 use wayland_client::{
     QueueHandle,
-    protocol::{wl_output::WlOutput, wl_pointer::WlPointer, wl_surface::WlSurface},
+    protocol::{wl_output::WlOutput, wl_pointer::WlPointer, wl_shm::Format, wl_surface::WlSurface},
 };
 
 use crate::{
-    components::{Label, Padding, RgbColor},
-    configuration::THEME_CONFIG,
-    widgets::{CenterWidgets, Widgets},
+    configuration::{BarLayer, Config, Edge, WidgetsConfig, load_config},
+    error::SimbarError,
+    layout::Rect,
+    modules::{ModuleSources, build_modules},
+    renderer,
+    widgets::{SimbarWidgets, WidgetTrees},
 };
 
 /// Represents the dimensions of a drawable surface in pixels.
@@ -66,14 +75,49 @@ impl From<(u32, u32)> for DrawSize {
     }
 }
 
+/// The scale a monitor renders its buffer at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceScale {
+    /// An integer `wl_surface` buffer scale.
+    Integer(i32),
+    /// A `wp_fractional_scale_v1` preferred scale, in 120ths.
+    Fractional(u32),
+}
+
+impl SurfaceScale {
+    /// Physical pixels per logical pixel.
+    pub fn factor(self) -> f32 {
+        match self {
+            Self::Integer(scale) => scale.max(1) as f32,
+            Self::Fractional(scale) => scale.max(1) as f32 / 120.0,
+        }
+    }
+
+    /// Converts a length in logical pixels to physical pixels.
+    ///
+    /// Fractional lengths are rounded half away from zero, as `wp_fractional_scale_v1`
+    /// recommends.
+    pub fn apply(self, length: u32) -> u32 {
+        match self {
+            Self::Integer(scale) => length * scale.max(1) as u32,
+            Self::Fractional(scale) => (length * scale.max(1) + 60) / 120,
+        }
+    }
+}
+
 /// Represents a monitor in the `SimBar` Wayland client, managing its Wayland surface and buffer.
 ///
 /// A `Monitor` corresponds to a single output (display) and handles its layer surface, shared
-/// memory pool, and rendering buffer. It tracks whether it is the primary monitor for rendering
-/// the status bar.
+/// memory pool, rendering buffer and its own widget tree, so every output renders and tracks
+/// damage independently.
 pub struct Monitor {
     /// The Wayland output (display) associated with this monitor.
     pub output: WlOutput,
+    /// The fractional scale and viewport objects of the surface, when the compositor
+    /// supports them.
+    ///
+    /// Declared before `layer_surface` so they are destroyed before the surface.
+    pub fractional: Option<FractionalSurface>,
     /// The layer surface for rendering the status bar on this monitor.
     pub layer_surface: LayerSurface,
     /// The shared memory pool for allocating buffers.
@@ -82,18 +126,180 @@ pub struct Monitor {
     ///
     /// If `None`, the monitor requires reconfiguration (e.g., creating a new buffer).
     pub buffer: Option<Buffer>,
-    /// The dimensions of the drawable area in pixels.
+    /// The dimensions of the drawable area in logical pixels.
     pub draw_size: DrawSize,
-    /// Whether this monitor is the primary one for rendering the status bar.
-    pub is_primary: bool,
+    /// The scale of the output the surface is shown on.
+    ///
+    /// Buffers are allocated at `draw_size * scale` physical pixels.
+    pub scale: SurfaceScale,
+    /// The configuration of the bar on this monitor, `[[output]]` overrides applied.
+    pub config: Config,
+    /// The widget tree rendered on this monitor, built from `config`.
+    pub widgets: SimbarWidgets,
+    /// The component rectangles of the frame currently in `buffer`.
+    ///
+    /// `None` when the buffer does not hold a frame yet and must be painted entirely.
+    pub drawn: Option<Vec<Rect>>,
+    /// Whether a frame callback was requested and has not fired yet.
+    ///
+    /// Draws are deferred while it is set, so the bar never paints faster than the
+    /// compositor presents.
+    pub frame_pending: bool,
+    /// Whether a draw was deferred until the pending frame callback fires.
+    pub needs_redraw: bool,
+}
+
+impl Monitor {
+    /// The dimensions of the buffer in physical pixels.
+    pub fn buffer_size(&self) -> DrawSize {
+        (
+            self.scale.apply(self.draw_size.width),
+            self.scale.apply(self.draw_size.height),
+        )
+            .into()
+    }
+
+    /// Places the layer surface on the output described by `info` as `config` says: edge,
+    /// layer, margin, size and exclusive zone.
+    ///
+    /// Without a configured `width` the length of the bar is left to the compositor, which
+    /// stretches it between the edges it is anchored to, less the margins, and tells the size
+    /// in the `configure` event; [`SimbarConfig::bar_size`] is only the expected size until
+    /// then.
+    ///
+    /// When the size actually changes, the current buffer is dropped, so the next `configure`
+    /// event allocates a buffer of the new size and redraws.
+    ///
+    /// [`SimbarConfig::bar_size`]: crate::configuration::SimbarConfig::bar_size
+    pub fn place(&mut self, info: &OutputInfo) {
+        let config = &self.config.simbar;
+        let draw_size: DrawSize = config.bar_size(info).into();
+        let direction = config.anchor.direction();
+
+        let anchor = match config.anchor {
+            Edge::Top => Anchor::TOP | Anchor::LEFT | Anchor::RIGHT,
+            Edge::Bottom => Anchor::BOTTOM | Anchor::LEFT | Anchor::RIGHT,
+            Edge::Left => Anchor::LEFT | Anchor::TOP | Anchor::BOTTOM,
+            Edge::Right => Anchor::RIGHT | Anchor::TOP | Anchor::BOTTOM,
+        };
+        let margin = config.margin;
+
+        self.layer_surface.set_anchor(anchor);
+        self.layer_surface.set_layer(shell_layer(config.layer));
+        self.layer_surface.set_margin(
+            margin.top as i32,
+            margin.right as i32,
+            margin.bottom as i32,
+            margin.left as i32,
+        );
+        // A length of 0 stretches the bar between the edges it is anchored to
+        let (width, height) = direction.join(config.width.unwrap_or(0), config.height);
+        self.layer_surface.set_size(width, height);
+        self.layer_surface
+            .set_exclusive_zone(config.exclusive_zone());
+
+        self.resize(draw_size);
+        self.layer_surface.commit();
+    }
+
+    /// Switches to a new surface size, dropping the current buffer when it changes.
+    pub fn resize(&mut self, draw_size: DrawSize) {
+        if self.draw_size != draw_size {
+            self.draw_size = draw_size;
+            self.drop_buffer();
+        }
+    }
+
+    /// Switches to a new output scale, returning whether it differs from the current one.
+    ///
+    /// On change, the current buffer is dropped; the caller allocates a new buffer through
+    /// [`Monitor::attach_buffer`] if the surface was already configured.
+    pub fn set_scale(&mut self, scale: SurfaceScale) -> bool {
+        if self.scale == scale {
+            return false;
+        }
+
+        self.scale = scale;
+        self.drop_buffer();
+
+        true
+    }
+
+    /// Allocates a buffer of [`Monitor::buffer_size`], growing the pool if needed, and
+    /// attaches it to the surface.
+    ///
+    /// The buffer holds no frame yet, so the next draw paints it entirely. On error the
+    /// monitor is left without a buffer and is not drawn.
+    pub fn attach_buffer(&mut self) -> Result<(), SimbarError> {
+        let size = self.buffer_size();
+        let stride = size.width * 4;
+
+        // Resize slot pool to match with new output size
+        self.pool
+            .resize((stride * size.height) as usize)
+            .map_err(SimbarError::ResizePool)?;
+
+        let (buffer, _) = self.pool.create_buffer(
+            size.width as i32,
+            size.height as i32,
+            stride as i32,
+            Format::Argb8888,
+        )?;
+
+        let surface = self.layer_surface.wl_surface();
+
+        buffer.attach_to(surface)?;
+
+        match (&self.fractional, self.scale) {
+            (Some(fractional), _) => fractional
+                .viewport
+                .set_destination(self.draw_size.width as i32, self.draw_size.height as i32),
+            (None, SurfaceScale::Integer(scale)) => surface.set_buffer_scale(scale),
+            (None, SurfaceScale::Fractional(_)) => {
+                unreachable!("fractional scale without viewport")
+            }
+        }
+
+        self.layer_surface.commit();
+
+        self.buffer = Some(buffer);
+        self.drawn = None;
+        // A callback requested for the previous buffer may never fire
+        self.frame_pending = false;
+
+        Ok(())
+    }
+
+    /// Drops the current buffer, the next one is allocated by [`Monitor::attach_buffer`].
+    fn drop_buffer(&mut self) {
+        drop(self.buffer.take());
+        self.drawn = None;
+    }
+}
+
+/// The layer-shell layer matching a configured [`BarLayer`].
+fn shell_layer(layer: BarLayer) -> Layer {
+    match layer {
+        BarLayer::Background => Layer::Background,
+        BarLayer::Bottom => Layer::Bottom,
+        BarLayer::Top => Layer::Top,
+        BarLayer::Overlay => Layer::Overlay,
+    }
 }
 
 /// The main state of the `SimBar` Wayland client, managing monitors and Wayland protocols.
 ///
 /// `SimBar` orchestrates the Wayland client’s interaction with the compositor, handling
-/// multiple monitors, input events, and rendering of the status bar. It maintains the
-/// state of Wayland protocols and tracks the last draw time for frame rate control.
+/// multiple monitors, input events, and rendering of the status bar. Drawing is event driven:
+/// the bar only paints when a module or a configuration change requests it, so an idle bar
+/// does not wake up at all.
 pub struct SimBar {
+    /// The configuration currently applied to every monitor.
+    pub config: Config,
+    /// The configuration file passed on the command line, if any.
+    ///
+    /// `None` means the XDG location is used.
+    pub config_path: Option<PathBuf>,
     /// The registry state for discovering Wayland globals.
     pub registry_state: RegistryState,
     /// The seat state for handling input devices (e.g., pointer).
@@ -106,80 +312,231 @@ pub struct SimBar {
     pub compositor: CompositorState,
     /// The layer shell state for creating layer surfaces (e.g., status bar).
     pub layer_shell: LayerShell,
-    /// The list of monitors managed by the client.
+    /// The fractional scaling globals, `None` when the compositor lacks either of them and
+    /// integer scaling is used instead.
+    pub fractional_scale: Option<FractionalScaleState>,
+    /// The event sources of the modules feeding content into the monitors' widgets.
+    pub module_sources: ModuleSources,
+    /// The monitors the bar is shown on, one per selected output.
     pub monitors: Vec<Monitor>,
     /// The optional pointer device for handling mouse events.
     pub pointer: Option<WlPointer>,
     /// Whether the client should exit.
     pub exit: bool,
-    /// The timestamp of the last rendered frame, used for frame rate capping.
-    pub last_frame_time: u32,
 }
 
 impl SimBar {
+    /// Re-reads the configuration file and applies it to every monitor.
+    ///
+    /// An invalid file is reported and ignored; the bar keeps running with the previous
+    /// configuration.
+    pub fn reload_config(&mut self, qh: &QueueHandle<Self>) {
+        match load_config(self.config_path.as_deref()) {
+            Ok(config) => {
+                println!("Configuration reloaded");
+                self.apply_config(qh, config);
+            }
+            Err(err) => eprintln!("Keeping previous configuration: {err}"),
+        }
+    }
+
+    /// Replaces the configuration, showing or hiding the bar on outputs according to the new
+    /// selection, rebuilding the widget trees whose configuration changed and redrawing the
+    /// rest in place.
+    pub fn apply_config(&mut self, qh: &QueueHandle<Self>, config: Config) {
+        self.config = config;
+        self.sync_monitors(qh);
+
+        // Resized monitors lost their buffer and are redrawn on `configure`
+        self.redraw(qh);
+    }
+
+    /// The widget trees of the monitors shown on `outputs`.
+    pub fn widget_trees(&self, outputs: &[WlOutput]) -> WidgetTrees<'_> {
+        self.monitors
+            .iter()
+            .filter(|monitor| outputs.contains(&monitor.output))
+            .map(|monitor| &monitor.widgets)
+            .collect()
+    }
+
+    /// Stops the running modules and starts them again for every monitor.
+    ///
+    /// Monitors sharing the same widget configuration share their modules. Modules fill
+    /// their components right away, so the next draw shows their content.
+    pub fn start_modules(&mut self) {
+        self.module_sources.clear();
+
+        let mut groups: Vec<(&WidgetsConfig, Vec<WlOutput>)> = Vec::new();
+
+        for monitor in self.monitors.iter() {
+            match groups
+                .iter_mut()
+                .find(|(widgets, _)| **widgets == monitor.config.widgets)
+            {
+                Some((_, outputs)) => outputs.push(monitor.output.clone()),
+                None => groups.push((&monitor.config.widgets, vec![monitor.output.clone()])),
+            }
+        }
+
+        for (config, outputs) in groups {
+            let widgets = self
+                .monitors
+                .iter()
+                .filter(|monitor| outputs.contains(&monitor.output))
+                .map(|monitor| &monitor.widgets)
+                .collect();
+
+            self.module_sources.set_outputs(outputs);
+
+            for module in build_modules(config) {
+                if let Err(err) = module.start(&widgets, &mut self.module_sources) {
+                    eprintln!("Failed to start module: {err}");
+                }
+            }
+        }
+    }
+
+    /// Requests a draw of every monitor that already has a buffer.
+    ///
+    /// Monitors waiting for a frame callback are drawn once it fires.
+    pub fn redraw(&mut self, qh: &QueueHandle<Self>) {
+        let surfaces: Vec<WlSurface> = self
+            .monitors
+            .iter()
+            .filter(|monitor| monitor.buffer.is_some())
+            .map(|monitor| monitor.layer_surface.wl_surface().clone())
+            .collect();
+
+        for surface in surfaces {
+            self.draw(qh, &surface);
+        }
+    }
+
+    /// Paints the widgets onto the monitor showing `surface` and commits it.
+    ///
+    /// Only the columns damaged since the previous frame are repainted; when nothing changed
+    /// the surface is not committed at all. While a frame callback is pending the draw is
+    /// deferred to [`SimBar::frame_done`].
+    ///
+    /// The whole frame is still laid out and composed each time: a component changing size
+    /// moves its neighbours, so the layout needs all of them, while unchanged labels reuse
+    /// their cached pixels. Damage only limits what is copied into the buffer and what the
+    /// compositor repaints.
     pub fn draw(&mut self, qh: &QueueHandle<Self>, surface: &WlSurface) {
         if let Some(monitor) = self
             .monitors
             .iter_mut()
             .find(|monitor| monitor.layer_surface.wl_surface() == surface)
         {
-            let buffer: &mut Buffer = monitor.buffer.as_mut().expect("Buffer should be created");
+            if monitor.frame_pending {
+                monitor.needs_redraw = true;
+                return;
+            }
 
-            let canvas: &mut [u8] = monitor.pool.raw_data_mut(&buffer.slot());
+            monitor.needs_redraw = false;
 
-            let hello: Label = Label {
-                text: "Hello".to_owned(),
-                fg_color: RgbColor::new(0xFF, 0xFF, 0xFF),
-                bg_color: None,
-                font_size: 25,
-            };
-            let pad = Padding(20);
-            let world: Label = Label {
-                text: "World".to_owned(),
-                fg_color: RgbColor::new(0xFF, 0x00, 0xFF),
-                bg_color: None,
-                font_size: 25,
-            };
+            let background_color = monitor.config.theme.background_color;
+            let widgets = &monitor.widgets;
+
+            if monitor.drawn.is_some() && !widgets.is_dirty() {
+                return;
+            }
 
-            let center = CenterWidgets {
-                components: vec![Box::new(hello), Box::new(pad), Box::new(world)],
-                height: monitor.draw_size.height,
+            let size = monitor.buffer_size();
+
+            // The buffer could not be allocated, see `Monitor::attach_buffer`
+            let Some(buffer) = monitor.buffer.as_mut() else {
+                return;
             };
 
-            let data = center.render(monitor.draw_size);
-
-            canvas
-                .chunks_exact_mut(4)
-                .enumerate()
-                .for_each(|(i, chunk)| {
-                    let pixel = if i < data.len() {
-                        match data[i] {
-                            Some(fg_pixel) => {
-                                fg_pixel.blend_with_background(THEME_CONFIG.background_color)
-                            }
-                            None => THEME_CONFIG.background_color,
-                        }
-                    } else {
-                        THEME_CONFIG.background_color
-                    };
-
-                    let array: &mut [u8; 4] = chunk.try_into().unwrap();
-                    *array = u32::from(pixel).to_le_bytes();
-                });
-
-            monitor.layer_surface.wl_surface().damage_buffer(
-                0,
-                0,
-                monitor.draw_size.width as i32,
-                monitor.draw_size.height as i32,
+            let canvas: &mut [u8] = monitor.pool.raw_data_mut(&buffer.slot());
+
+            let direction = monitor.config.simbar.anchor.direction();
+            let (length, thickness) = direction.split((size.width, size.height));
+
+            let frame = renderer::render(widgets, size, monitor.scale.factor(), background_color);
+            let damage = damaged_spans(
+                direction,
+                length,
+                monitor.drawn.as_deref(),
+                &frame.placements,
             );
 
+            widgets.clear_dirty();
+            monitor.drawn = Some(frame.placements.iter().map(|p| p.rect).collect());
+
+            if damage.is_empty() {
+                return;
+            }
+
+            for &(start, end) in damage.iter() {
+                let band = direction.band(start, end, thickness);
+                frame.copy_band(band, canvas);
+
+                monitor.layer_surface.wl_surface().damage_buffer(
+                    band.x,
+                    band.y,
+                    band.width as i32,
+                    band.height as i32,
+                );
+            }
+
             monitor
                 .layer_surface
                 .wl_surface()
                 .frame(qh, monitor.layer_surface.wl_surface().clone());
+            monitor.frame_pending = true;
 
             monitor.layer_surface.commit();
         }
     }
+
+    /// Applies the new scale of the output `surface` is shown on, reallocating and redrawing
+    /// its buffer when the scale changed.
+    ///
+    /// Integer scales are ignored on surfaces following a fractional scale.
+    pub fn set_scale(&mut self, qh: &QueueHandle<Self>, surface: &WlSurface, scale: SurfaceScale) {
+        let Some(monitor) = self
+            .monitors
+            .iter_mut()
+            .find(|monitor| monitor.layer_surface.wl_surface() == surface)
+        else {
+            return;
+        };
+
+        if monitor.fractional.is_some() && matches!(scale, SurfaceScale::Integer(_)) {
+            return;
+        }
+
+        let configured = monitor.buffer.is_some();
+
+        // An unconfigured surface gets its buffer on `configure`
+        if monitor.set_scale(scale) && configured {
+            match monitor.attach_buffer() {
+                Ok(()) => self.draw(qh, surface),
+                Err(err) => eprintln!("Cannot rescale the bar, hiding it: {err}"),
+            }
+        }
+    }
+
+    /// Handles the frame callback of `surface`, running the draw deferred while it was
+    /// pending, if any.
+    ///
+    /// No new callback is requested unless that draw commits something.
+    pub fn frame_done(&mut self, qh: &QueueHandle<Self>, surface: &WlSurface) {
+        let Some(monitor) = self
+            .monitors
+            .iter_mut()
+            .find(|monitor| monitor.layer_surface.wl_surface() == surface)
+        else {
+            return;
+        };
+
+        monitor.frame_pending = false;
+
+        if monitor.needs_redraw && monitor.buffer.is_some() {
+            self.draw(qh, surface);
+        }
+    }
 }
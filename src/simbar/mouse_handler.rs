@@ -22,10 +22,9 @@ impl PointerHandler for SimBar {
                 .monitors
                 .iter()
                 .any(|monitor| &event.surface == monitor.layer_surface.wl_surface())
+                && let PointerEventKind::Press { .. } = event.kind
             {
-                if let PointerEventKind::Press { .. } = event.kind {
-                    self.exit = true; // Exit on any button press
-                }
+                self.exit = true; // Exit on any button press
             }
         }
     }
@@ -1,16 +1,18 @@
 use smithay_client_toolkit::{
     delegate_output,
-    output::{OutputHandler, OutputState},
-    shell::{
-        WaylandSurface,
-        wlr_layer::{Anchor, Layer},
-    },
-    shm::slot::{Buffer, SlotPool},
+    output::{OutputHandler, OutputInfo, OutputState},
+    shell::WaylandSurface,
+    shm::slot::SlotPool,
 };
 use wayland_client::{Connection, QueueHandle, protocol::wl_output::WlOutput};
 
-use super::SimBar;
-use crate::{configuration::SIMBAR_CONFIG, simbar::Monitor};
+use super::{SimBar, shell_layer};
+use crate::{
+    configuration::Config,
+    error::SimbarError,
+    simbar::{Monitor, SurfaceScale},
+    widgets::SimbarWidgets,
+};
 
 delegate_output!(SimBar);
 
@@ -19,106 +21,146 @@ impl OutputHandler for SimBar {
         &mut self.output_state
     }
 
-    fn new_output(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, output: WlOutput) {
-        if let Some(info) = self.output_state.info(&output) {
-            println!(
-                "Create surface for monitor: {}",
-                info.clone().name.unwrap_or("Unknown".to_string())
-            );
-            // Create new wayland surface
-            let surface = self.compositor.create_surface(qh);
-
-            // Create new wayland layer for current output
-            let layer_surface = self.layer_shell.create_layer_surface(
-                qh,
-                surface,
-                Layer::Overlay,
-                Some("sim_bar"),
-                Some(&output),
-            );
-
-            let output_width = info
-                .logical_size
-                .map_or(SIMBAR_CONFIG.width_fallback, |(w, _)| w as u32);
-
-            let width = SIMBAR_CONFIG.width.unwrap_or(output_width);
-            let height = SIMBAR_CONFIG.height;
-            let depth = 4;
-
-            let is_primary = SIMBAR_CONFIG
-                .primary_output
-                .map_or(info.logical_position == Some((0, 0)), |name| {
-                    info.name.is_some_and(|monitor_name| name == monitor_name)
-                });
-
-            layer_surface.set_anchor(Anchor::TOP | Anchor::LEFT | Anchor::RIGHT);
-            layer_surface.set_size(width, height);
-            layer_surface.set_exclusive_zone(height as i32);
-            layer_surface.commit();
-
-            let pool = SlotPool::new((width * height * depth) as usize, &self.shm)
-                .expect("Failed to create pool");
-
-            println!("Create new monitor: {width} x {height}");
-
-            self.monitors.push(Monitor {
-                output,
-                layer_surface,
-                pool,
-                draw_size: (width, height).into(),
-                is_primary,
-                buffer: None,
-            });
-        }
+    fn new_output(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.sync_monitors(qh);
     }
 
-    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, output: WlOutput) {
+    fn update_output(&mut self, _conn: &Connection, qh: &QueueHandle<Self>, _output: WlOutput) {
         println!("update_output");
 
-        if let Some(monitor) = self
-            .monitors
-            .iter_mut()
-            .find(|monitor| monitor.output == output)
-        {
-            if let Some(info) = self.output_state.info(&output) {
-                let output_width = info
-                    .logical_size
-                    .map_or(SIMBAR_CONFIG.width_fallback, |(w, _)| w as u32);
-
-                let old_draw_size = monitor.draw_size;
-
-                let width = SIMBAR_CONFIG.width.unwrap_or(output_width);
-                let height = SIMBAR_CONFIG.height;
-
-                monitor.layer_surface.set_size(width, height);
-                monitor.layer_surface.set_exclusive_zone(height as i32);
+        self.sync_monitors(qh);
+    }
 
-                monitor.draw_size = (width, height).into();
+    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, output: WlOutput) {
+        println!("output_destroyed");
+        self.monitors.retain(|monitor| monitor.output != output);
+    }
+}
 
-                if old_draw_size != monitor.draw_size {
-                    // Remove old buffer anyway
-                    if monitor.buffer.is_some() {
-                        let old_buffer: Buffer =
-                            monitor.buffer.take().expect("Failed to take old buffer");
+impl SimBar {
+    /// Matches the monitors with the outputs selected by the configuration.
+    ///
+    /// Bars are created on newly selected outputs and removed from outputs no longer
+    /// selected; the others get their configuration updated and are resized in place.
+    /// Modules are restarted when a widget tree was created or rebuilt so it gets filled
+    /// right away. An output the bar cannot be created on is skipped.
+    pub fn sync_monitors(&mut self, qh: &QueueHandle<Self>) {
+        let outputs: Vec<(WlOutput, OutputInfo)> = self
+            .output_state
+            .outputs()
+            .filter_map(|output| {
+                let info = self.output_state.info(&output)?;
+                Some((output, info))
+            })
+            .collect();
+
+        self.monitors.retain(|monitor| {
+            outputs.iter().any(|(output, info)| {
+                *output == monitor.output && self.config.simbar.shows_on(info)
+            })
+        });
+
+        let mut changed = false;
+
+        for (output, info) in outputs {
+            if !self.config.simbar.shows_on(&info) {
+                continue;
+            }
 
-                        // Destroy old buffer
-                        drop(old_buffer);
+            let config = self.config.for_output(&info);
+
+            match self
+                .monitors
+                .iter_mut()
+                .find(|monitor| monitor.output == output)
+            {
+                Some(monitor) => {
+                    if monitor.config != config {
+                        monitor.widgets = SimbarWidgets::from_config(&config);
+                        monitor.config = config;
+                        // Nothing drawn before is kept: the widgets and the theme may differ
+                        monitor.drawn = None;
+                        changed = true;
                     }
 
-                    // Resize slot pool to match with new output size
-                    monitor
-                        .pool
-                        .resize((width * height * 4) as usize)
-                        .expect("Failed to create pool.");
+                    monitor.place(&info);
                 }
-
-                monitor.layer_surface.commit();
+                None => match self.add_monitor(qh, output, &info, config) {
+                    Ok(()) => changed = true,
+                    Err(err) => eprintln!(
+                        "Cannot show the bar on {}: {err}",
+                        info.name.as_deref().unwrap_or("Unknown")
+                    ),
+                },
             }
         }
+
+        if changed {
+            self.start_modules();
+        }
     }
 
-    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, output: WlOutput) {
-        println!("output_destroyed");
-        self.monitors.retain(|monitor| monitor.output != output);
+    /// Creates the layer surface and monitor of an output the bar is shown on.
+    fn add_monitor(
+        &mut self,
+        qh: &QueueHandle<Self>,
+        output: WlOutput,
+        info: &OutputInfo,
+        config: Config,
+    ) -> Result<(), SimbarError> {
+        println!(
+            "Create surface for monitor: {}",
+            info.clone().name.unwrap_or("Unknown".to_string())
+        );
+        // Create new wayland surface
+        let surface = self.compositor.create_surface(qh);
+
+        // Create new wayland layer for current output
+        let layer_surface = self.layer_shell.create_layer_surface(
+            qh,
+            surface,
+            shell_layer(config.simbar.layer),
+            Some("sim_bar"),
+            Some(&output),
+        );
+
+        let (width, height) = config.simbar.bar_size(info);
+        let depth = 4;
+
+        let fractional = self
+            .fractional_scale
+            .as_ref()
+            .map(|state| state.get_surface(layer_surface.wl_surface(), qh));
+
+        // Until the compositor tells otherwise, assume the surface uses the output scale
+        let scale = match fractional {
+            Some(_) => SurfaceScale::Fractional(info.scale_factor.max(1) as u32 * 120),
+            None => SurfaceScale::Integer(info.scale_factor.max(1)),
+        };
+        let buffer_size = (scale.apply(width), scale.apply(height));
+
+        let pool = SlotPool::new((buffer_size.0 * buffer_size.1 * depth) as usize, &self.shm)?;
+
+        println!("Create new monitor: {width} x {height}");
+
+        let mut monitor = Monitor {
+            output,
+            fractional,
+            layer_surface,
+            pool,
+            draw_size: (width, height).into(),
+            scale,
+            widgets: SimbarWidgets::from_config(&config),
+            config,
+            buffer: None,
+            drawn: None,
+            frame_pending: false,
+            needs_redraw: false,
+        };
+
+        monitor.place(info);
+        self.monitors.push(monitor);
+
+        Ok(())
     }
 }
@@ -27,11 +27,10 @@ impl SeatHandler for SimBar {
         println!("new_capability");
         if capability == Capability::Pointer && self.pointer.is_none() {
             println!("Set pointer capability");
-            let pointer = self
-                .seat_state
-                .get_pointer(qh, &seat)
-                .expect("Failed to create pointer");
-            self.pointer = Some(pointer);
+            match self.seat_state.get_pointer(qh, &seat) {
+                Ok(pointer) => self.pointer = Some(pointer),
+                Err(err) => eprintln!("Pointer input is not available: {err}"),
+            }
         }
     }
 
@@ -43,8 +42,10 @@ impl SeatHandler for SimBar {
         capability: Capability,
     ) {
         println!("remove_capability");
-        if capability == Capability::Pointer && self.pointer.is_some() {
-            self.pointer.take().unwrap().release();
+        if capability == Capability::Pointer
+            && let Some(pointer) = self.pointer.take()
+        {
+            pointer.release();
         }
     }
 
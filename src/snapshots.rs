@@ -0,0 +1,407 @@
+//! Golden-image tests for components and widget trees.
+//!
+//! Each test renders at a fixed size and compares the result with a reference image in
+//! `tests/snapshots/<name>.png`. On a mismatch the rendered image and a diff highlighting the
+//! differing pixels in red are written to `target/snapshots/` for inspection.
+//!
+//! A missing reference fails the test. Run with `UPDATE_SNAPSHOTS=1` to record new references
+//! or re-record every one after an intended rendering change, then review and commit the
+//! images. Text is drawn with the font vendored in `tests/fonts/`, see
+//! [`fonts::embedded_font`](crate::fonts::embedded_font).
+
+use std::{
+    env,
+    fs::{self, File},
+    path::PathBuf,
+};
+
+mod bitmap_font;
+
+use crate::{
+    components::{
+        ArgbColor, Component, Label, MaxWidth, Padding, RgbColor, Truncation, VerticalText,
+    },
+    configuration::Config,
+    fonts,
+    layout::Direction,
+    renderer::{self, Frame},
+    simbar::DrawSize,
+    widgets::{CenterWidgets, SimbarWidgets, Widgets, composite},
+};
+
+/// The largest difference allowed on any channel of a pixel, absorbing rounding differences
+/// of the rasterizer between platforms.
+const TOLERANCE: u8 = 2;
+
+const BACKGROUND: ArgbColor = ArgbColor::new(1.0, 0x20, 0x20, 0x20);
+const WHITE: RgbColor = RgbColor::new(0xFF, 0xFF, 0xFF);
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.png"))
+}
+
+fn output_path(name: &str, suffix: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("target/snapshots")
+        .join(format!("{name}.{suffix}.png"))
+}
+
+/// Reads an RGBA PNG back into a frame.
+fn read_png(path: &PathBuf) -> Frame {
+    let decoder = png::Decoder::new(File::open(path).expect("Failed to open reference"));
+    let mut reader = decoder
+        .read_info()
+        .expect("Failed to read reference header");
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut data)
+        .expect("Failed to decode reference");
+
+    assert_eq!(
+        (info.color_type, info.bit_depth),
+        (png::ColorType::Rgba, png::BitDepth::Eight),
+        "Reference {} is not an 8-bit RGBA image",
+        path.display()
+    );
+
+    let pixels = data[..info.buffer_size()]
+        .chunks_exact(4)
+        .map(|pixel| u32::from_le_bytes([pixel[2], pixel[1], pixel[0], pixel[3]]))
+        .collect();
+
+    Frame {
+        size: (info.width, info.height).into(),
+        pixels,
+        placements: Vec::new(),
+    }
+}
+
+/// Whether every channel of `a` is within [`TOLERANCE`] of the one of `b`.
+fn close(a: u32, b: u32) -> bool {
+    a.to_le_bytes()
+        .into_iter()
+        .zip(b.to_le_bytes())
+        .all(|(a, b)| a.abs_diff(b) <= TOLERANCE)
+}
+
+/// The reference dimmed, with the pixels differing from `actual` in red.
+fn diff_image(expected: &Frame, actual: &Frame) -> Frame {
+    let pixels = expected
+        .pixels
+        .iter()
+        .zip(&actual.pixels)
+        .map(|(&expected, &actual)| {
+            if close(expected, actual) {
+                let [blue, green, red, _] = expected.to_le_bytes();
+                let gray = (u32::from(red) + u32::from(green) + u32::from(blue)) / 9;
+                0xFF00_0000 | gray << 16 | gray << 8 | gray
+            } else {
+                0xFFFF_0000
+            }
+        })
+        .collect();
+
+    Frame {
+        size: expected.size,
+        pixels,
+        placements: Vec::new(),
+    }
+}
+
+/// Compares `actual` with the reference image `name`, see the module documentation.
+fn assert_snapshot(name: &str, actual: &Frame) {
+    let reference = reference_path(name);
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some_and(|value| value != "0");
+
+    if update {
+        fs::create_dir_all(reference.parent().unwrap()).expect("Failed to create snapshot dir");
+        actual
+            .write_png(&reference)
+            .expect("Failed to write reference");
+        eprintln!("Recorded snapshot {}", reference.display());
+        return;
+    }
+
+    assert!(
+        reference.exists(),
+        "Missing reference {}, record it with UPDATE_SNAPSHOTS=1",
+        reference.display()
+    );
+
+    let expected = read_png(&reference);
+    let actual_path = output_path(name, "actual");
+    let diff_path = output_path(name, "diff");
+
+    let write_failure = |diff: Option<Frame>| {
+        fs::create_dir_all(actual_path.parent().unwrap()).expect("Failed to create output dir");
+        actual
+            .write_png(&actual_path)
+            .expect("Failed to write rendered image");
+        if let Some(diff) = diff {
+            diff.write_png(&diff_path).expect("Failed to write diff");
+        }
+    };
+
+    if expected.size != actual.size {
+        write_failure(None);
+        panic!(
+            "Snapshot `{name}` is {}x{} but the reference is {}x{}, see {}",
+            actual.size.width,
+            actual.size.height,
+            expected.size.width,
+            expected.size.height,
+            actual_path.display()
+        );
+    }
+
+    let differing = expected
+        .pixels
+        .iter()
+        .zip(&actual.pixels)
+        .filter(|&(&expected, &actual)| !close(expected, actual))
+        .count();
+
+    if differing > 0 {
+        write_failure(Some(diff_image(&expected, actual)));
+        panic!(
+            "Snapshot `{name}` differs from the reference on {differing} pixels, see {} and {}",
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+/// Renders a single line of `components` centered in a canvas of `size`.
+fn render_line(
+    components: Vec<Box<dyn Component + Send + Sync>>,
+    size: (u32, u32),
+    scale: f32,
+    direction: Direction,
+) -> Frame {
+    let size: DrawSize = size.into();
+    let (_, cross) = direction.split((size.width, size.height));
+    let line = CenterWidgets {
+        components,
+        thickness: (cross as f32 / scale).round() as u32,
+        direction,
+    };
+
+    Frame::new(line.render(size, scale), size, BACKGROUND)
+}
+
+fn label(text: &str) -> Label {
+    Label::new(text, WHITE, None, 18)
+}
+
+#[test]
+fn label_row() {
+    let frame = render_line(
+        vec![Box::new(label("SimBar"))],
+        (120, 24),
+        1.0,
+        Direction::Row,
+    );
+    assert_snapshot("label_row", &frame);
+}
+
+#[test]
+fn label_row_scaled() {
+    let frame = render_line(
+        vec![Box::new(label("SimBar"))],
+        (240, 48),
+        2.0,
+        Direction::Row,
+    );
+    assert_snapshot("label_row_scaled", &frame);
+}
+
+#[test]
+fn label_background() {
+    let label = Label::new("12:45", RgbColor::new(0, 0, 0), Some(WHITE), 18);
+    let frame = render_line(vec![Box::new(label)], (120, 24), 1.0, Direction::Row);
+    assert_snapshot("label_background", &frame);
+}
+
+#[test]
+fn label_rotated() {
+    let label = label("SimBar").with_vertical_text(VerticalText::Rotated);
+    let frame = render_line(vec![Box::new(label)], (24, 120), 1.0, Direction::Column);
+    assert_snapshot("label_rotated", &frame);
+}
+
+#[test]
+fn label_stacked() {
+    let label = label("Sim").with_vertical_text(VerticalText::Stacked);
+    let frame = render_line(vec![Box::new(label)], (24, 120), 1.0, Direction::Column);
+    assert_snapshot("label_stacked", &frame);
+}
+
+#[test]
+fn label_stacked_marks() {
+    // Both "é" are an "e" followed by a combining accent, which stays on its line
+    let label = label("e\u{301}te\u{301}").with_vertical_text(VerticalText::Stacked);
+    let frame = render_line(vec![Box::new(label)], (24, 120), 1.0, Direction::Column);
+    assert_snapshot("label_stacked_marks", &frame);
+}
+
+#[test]
+fn label_colr_glyphs() {
+    // Gradients, sweeps, composites and transforms from the COLRv1 test font
+    let fonts = fonts::chain_from_data(include_bytes!("../tests/fonts/colr_1.ttf"));
+    let label = Label::new(
+        "\u{F0100}\u{F0200}\u{F0300}\u{F0500}\u{F0600}\u{F0700}",
+        WHITE,
+        None,
+        24,
+    )
+    .with_fonts(fonts);
+    let frame = render_line(vec![Box::new(label)], (200, 32), 1.0, Direction::Row);
+    assert_snapshot("label_colr_glyphs", &frame);
+}
+
+#[test]
+fn label_bitmap_glyph() {
+    let fonts = fonts::chain_from_data(Vec::leak(bitmap_font::build()));
+    let glyph = |size| {
+        let text = format!("A{}B", bitmap_font::CHARACTER);
+        Box::new(Label::new(&text, WHITE, None, size).with_fonts(fonts))
+            as Box<dyn Component + Send + Sync>
+    };
+    let components = vec![glyph(16), Box::new(Padding(10)), glyph(30)];
+    let frame = render_line(components, (160, 40), 1.0, Direction::Row);
+    assert_snapshot("label_bitmap_glyph", &frame);
+}
+
+#[test]
+fn label_empty() {
+    let frame = render_line(vec![Box::new(label(""))], (120, 24), 1.0, Direction::Row);
+    assert_snapshot("label_empty", &frame);
+}
+
+#[test]
+fn label_truncated() {
+    let truncated = |truncation| {
+        Box::new(label("Long window title").with_max_width(Some(MaxWidth::Pixels(90)), truncation))
+            as Box<dyn Component + Send + Sync>
+    };
+    let components = vec![
+        truncated(Truncation::End),
+        Box::new(Padding(10)),
+        truncated(Truncation::Middle),
+        Box::new(Padding(10)),
+        truncated(Truncation::Start),
+    ];
+    let frame = render_line(components, (320, 24), 1.0, Direction::Row);
+    assert_snapshot("label_truncated", &frame);
+}
+
+#[test]
+fn label_truncated_chars() {
+    let label =
+        label("Long window title").with_max_width(Some(MaxWidth::Chars(8)), Truncation::End);
+    let frame = render_line(vec![Box::new(label)], (120, 24), 1.0, Direction::Row);
+    assert_snapshot("label_truncated_chars", &frame);
+}
+
+#[test]
+fn label_truncated_below_ellipsis() {
+    // Not even the ellipsis fits, nothing is drawn
+    let label =
+        label("Long window title").with_max_width(Some(MaxWidth::Pixels(2)), Truncation::Middle);
+    let frame = render_line(vec![Box::new(label)], (120, 24), 1.0, Direction::Row);
+    assert_snapshot("label_truncated_below_ellipsis", &frame);
+}
+
+#[test]
+fn padding_row() {
+    let components: Vec<Box<dyn Component + Send + Sync>> = vec![
+        Box::new(label("A")),
+        Box::new(Padding(20)),
+        Box::new(label("B")),
+    ];
+    let frame = render_line(components, (120, 24), 1.0, Direction::Row);
+    assert_snapshot("padding_row", &frame);
+}
+
+#[test]
+fn padding_column() {
+    let components: Vec<Box<dyn Component + Send + Sync>> = vec![
+        Box::new(label("A").with_vertical_text(VerticalText::Stacked)),
+        Box::new(Padding(20)),
+        Box::new(label("B").with_vertical_text(VerticalText::Stacked)),
+    ];
+    let frame = render_line(components, (24, 120), 1.0, Direction::Column);
+    assert_snapshot("padding_column", &frame);
+}
+
+#[test]
+fn center_widgets_clipped() {
+    let frame = render_line(
+        vec![Box::new(label("A label wider than the bar"))],
+        (80, 24),
+        1.0,
+        Direction::Row,
+    );
+    assert_snapshot("center_widgets_clipped", &frame);
+}
+
+#[test]
+fn center_widgets_overlapping_regions() {
+    let size: DrawSize = (120, 24).into();
+    let region = |text: &str| CenterWidgets {
+        components: vec![Box::new(label(text))],
+        thickness: 24,
+        direction: Direction::Row,
+    };
+    let (below, above) = (region("WWWWWWWW"), region("ii"));
+
+    let rendered = composite(size, 1.0, Direction::Row, &[&below, &above]);
+    assert_snapshot(
+        "center_widgets_overlapping_regions",
+        &Frame::new(rendered, size, BACKGROUND),
+    );
+}
+
+fn bar(anchor: &str, size: (u32, u32)) -> Frame {
+    let config: Config = toml::from_str(&format!(
+        r##"
+        [simbar]
+        anchor = "{anchor}"
+        height = 24
+
+        [[widgets.left]]
+        type = "label"
+        text = "L"
+        fg_color = "#FF8000"
+        font_size = 18
+
+        [[widgets.center]]
+        type = "label"
+        text = "Center"
+        fg_color = "#FFFFFF"
+        font_size = 18
+
+        [[widgets.right]]
+        type = "label"
+        text = "R"
+        fg_color = "#0080FF"
+        font_size = 18
+        "##
+    ))
+    .expect("Invalid snapshot config");
+
+    let widgets = SimbarWidgets::from_config(&config);
+    renderer::render(&widgets, size.into(), 1.0, BACKGROUND)
+}
+
+#[test]
+fn bar_horizontal() {
+    assert_snapshot("bar_horizontal", &bar("top", (200, 24)));
+}
+
+#[test]
+fn bar_vertical() {
+    assert_snapshot("bar_vertical", &bar("left", (24, 200)));
+}
@@ -0,0 +1,193 @@
+//! A minimal font with a single glyph drawn from a color bitmap of its `CBDT` table, built
+//! when the tests run so that the image it holds is known exactly.
+
+/// The character mapped to the glyph.
+pub const CHARACTER: char = '\u{E000}';
+
+const UNITS_PER_EM: u16 = 16;
+const ASCENT: i16 = 13;
+const DESCENT: i16 = -3;
+/// Pixels per em of the only strike, whose image is an em wide and the line high.
+const PPEM: u8 = 16;
+
+/// The font data: the tables of a font with a `.notdef` glyph and the bitmap glyph, an em
+/// wide each.
+pub fn build() -> Vec<u8> {
+    // Sorted by tag, as the table directory requires
+    let tables = [
+        (b"CBDT", cbdt()),
+        (b"CBLC", cblc()),
+        (b"cmap", cmap()),
+        (b"head", head()),
+        (b"hhea", hhea()),
+        (b"hmtx", hmtx()),
+        (b"maxp", maxp()),
+    ];
+
+    let mut font = Vec::new();
+    font.extend(0x0001_0000_u32.to_be_bytes());
+    font.extend((tables.len() as u16).to_be_bytes());
+    // Search range, entry selector and range shift, which parsers do without
+    font.extend([0; 6]);
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in &tables {
+        font.extend(*tag);
+        // Checksums are not verified
+        font.extend(0_u32.to_be_bytes());
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+
+    for (_, data) in &tables {
+        font.extend(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    font
+}
+
+/// A square in four colors: red, green, blue and half transparent white from the top left.
+fn image() -> Vec<u8> {
+    let size = u32::from(PPEM);
+    let mut pixels = Vec::new();
+
+    for y in 0..size {
+        for x in 0..size {
+            pixels.extend(match (x < size / 2, y < size / 2) {
+                (true, true) => [0xFF, 0x00, 0x00, 0xFF],
+                (false, true) => [0x00, 0xFF, 0x00, 0xFF],
+                (true, false) => [0x00, 0x00, 0xFF, 0xFF],
+                (false, false) => [0xFF, 0xFF, 0xFF, 0x80],
+            });
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, size, size);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .expect("Failed to encode bitmap");
+
+    data
+}
+
+/// The bitmap data: the image of the glyph, in format 17, with small metrics.
+fn cbdt() -> Vec<u8> {
+    let image = image();
+
+    let mut cbdt = Vec::new();
+    cbdt.extend(0x0003_0000_u32.to_be_bytes());
+    // Height, width, bearing x, bearing y up to the top edge and advance
+    cbdt.extend([PPEM, PPEM, 0, ASCENT as u8, PPEM]);
+    cbdt.extend((image.len() as u32).to_be_bytes());
+    cbdt.extend(image);
+    cbdt
+}
+
+/// The bitmap locations: a single strike holding glyph 1, at offset 4 of the bitmap data.
+fn cblc() -> Vec<u8> {
+    let image_length = 5 + 4 + image().len() as u32;
+
+    let mut cblc = Vec::new();
+    cblc.extend(0x0003_0000_u32.to_be_bytes());
+    cblc.extend(1_u32.to_be_bytes());
+
+    // The bitmap size record, with its index subtables right after it
+    cblc.extend(56_u32.to_be_bytes());
+    cblc.extend(24_u32.to_be_bytes());
+    cblc.extend(1_u32.to_be_bytes());
+    cblc.extend(0_u32.to_be_bytes());
+    // Horizontal then vertical line metrics
+    for _ in 0..2 {
+        cblc.extend([ASCENT as u8, DESCENT as u8, PPEM, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+    cblc.extend(1_u16.to_be_bytes());
+    cblc.extend(1_u16.to_be_bytes());
+    // Pixels per em, bit depth and horizontal metrics flag
+    cblc.extend([PPEM, PPEM, 32, 1]);
+
+    // The index subtable array, then its only subtable in format 1
+    cblc.extend(1_u16.to_be_bytes());
+    cblc.extend(1_u16.to_be_bytes());
+    cblc.extend(8_u32.to_be_bytes());
+    cblc.extend(1_u16.to_be_bytes());
+    cblc.extend(17_u16.to_be_bytes());
+    cblc.extend(4_u32.to_be_bytes());
+    cblc.extend(0_u32.to_be_bytes());
+    cblc.extend(image_length.to_be_bytes());
+    cblc
+}
+
+/// Maps [`CHARACTER`] to glyph 1, with a Windows full repertoire subtable in format 12.
+fn cmap() -> Vec<u8> {
+    let mut cmap = Vec::new();
+    cmap.extend(0_u16.to_be_bytes());
+    cmap.extend(1_u16.to_be_bytes());
+    cmap.extend(3_u16.to_be_bytes());
+    cmap.extend(10_u16.to_be_bytes());
+    cmap.extend(12_u32.to_be_bytes());
+
+    cmap.extend(12_u16.to_be_bytes());
+    cmap.extend(0_u16.to_be_bytes());
+    cmap.extend(28_u32.to_be_bytes());
+    cmap.extend(0_u32.to_be_bytes());
+    cmap.extend(1_u32.to_be_bytes());
+    cmap.extend(u32::from(CHARACTER).to_be_bytes());
+    cmap.extend(u32::from(CHARACTER).to_be_bytes());
+    cmap.extend(1_u32.to_be_bytes());
+    cmap
+}
+
+fn head() -> Vec<u8> {
+    let mut head = Vec::new();
+    head.extend(0x0001_0000_u32.to_be_bytes());
+    // Font revision, checksum adjustment
+    head.extend([0; 8]);
+    head.extend(0x5F0F_3CF5_u32.to_be_bytes());
+    head.extend(0_u16.to_be_bytes());
+    head.extend(UNITS_PER_EM.to_be_bytes());
+    // Created and modified dates
+    head.extend([0; 16]);
+    for bound in [0, DESCENT, UNITS_PER_EM as i16, ASCENT] {
+        head.extend(bound.to_be_bytes());
+    }
+    // Mac style, lowest readable size, direction hint, short offsets and glyph data format
+    for value in [0_i16, 8, 2, 0, 0] {
+        head.extend(value.to_be_bytes());
+    }
+    head
+}
+
+fn hhea() -> Vec<u8> {
+    let mut hhea = Vec::new();
+    hhea.extend(0x0001_0000_u32.to_be_bytes());
+    // Ascent, descent, line gap, maximum advance, minimum side bearings, maximum extent,
+    // caret slope and offset, four reserved values and the metric data format
+    for value in [ASCENT, DESCENT, 0, 16, 0, 0, 16, 1, 0, 0, 0, 0, 0, 0, 0] {
+        hhea.extend(value.to_be_bytes());
+    }
+    // Number of horizontal metrics
+    hhea.extend(2_u16.to_be_bytes());
+    hhea
+}
+
+/// Both glyphs advance by an em.
+fn hmtx() -> Vec<u8> {
+    [UNITS_PER_EM, 0]
+        .repeat(2)
+        .into_iter()
+        .flat_map(u16::to_be_bytes)
+        .collect()
+}
+
+fn maxp() -> Vec<u8> {
+    let mut maxp = Vec::new();
+    maxp.extend(0x0000_5000_u32.to_be_bytes());
+    maxp.extend(2_u16.to_be_bytes());
+    maxp
+}
@@ -1,16 +1,28 @@
-use crate::{components::Component, simbar::DrawSize};
+use crate::{
+    components::Component,
+    layout::{Align, Direction},
+    simbar::DrawSize,
+};
 
-use super::{ArgbColor, Widgets};
+use super::{Rendered, Widgets, render_line};
 
 #[allow(unused)]
 pub struct LeftWidgets {
     pub components: Vec<Box<dyn Component + Send + Sync>>,
-    pub height: u32,
+    pub thickness: u32,
+    pub direction: Direction,
 }
 
 #[allow(unused)]
 impl Widgets for LeftWidgets {
-    fn render(&self, area: DrawSize) -> Vec<Option<ArgbColor>> {
-        todo!()
+    fn render(&self, area: DrawSize, scale: f32) -> Rendered {
+        render_line(
+            &self.components,
+            area,
+            scale,
+            self.direction,
+            self.thickness,
+            Align::Start,
+        )
     }
 }
@@ -2,8 +2,14 @@ mod center_widget;
 mod left_widgets;
 mod right_widgets;
 
-use crate::{components::ArgbColor, simbar::DrawSize};
-use std::sync::{OnceLock, RwLock};
+use crate::{
+    components::{ArgbColor, BoundingBox, Component, build_component},
+    configuration::{ComponentConfig, Config},
+    fonts,
+    layout::{Align, Direction, Node, Rect},
+    simbar::DrawSize,
+};
+use std::{any::Any, collections::HashMap, sync::RwLock};
 
 pub use center_widget::CenterWidgets;
 pub use left_widgets::LeftWidgets;
@@ -11,12 +17,377 @@ pub use right_widgets::RightWidgets;
 
 #[allow(unused)]
 pub trait Widgets {
-    fn render(&self, area: DrawSize) -> Vec<Option<ArgbColor>>;
+    /// Renders the region into a canvas of `area` physical pixels, at `scale` physical pixels
+    /// per logical pixel.
+    fn render(&self, area: DrawSize, scale: f32) -> Rendered;
 }
 
-#[allow(unused)]
+/// Where a component landed on the canvas and whether it changed since it was last drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub rect: Rect,
+    pub dirty: bool,
+}
+
+/// The pixels of a region, or of the whole bar, with the placement of every component.
+pub struct Rendered {
+    pub pixels: Vec<Option<ArgbColor>>,
+    /// One entry per component, in region then component order.
+    pub placements: Vec<Placement>,
+}
+
+/// One of the three regions of the bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Left,
+    Center,
+    Right,
+}
+
+impl Region {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+        }
+    }
+}
+
+/// The key a component is registered under in [`SimbarWidgets`].
+///
+/// This is its configured `id`, or `<region>.<index>` (e.g. `center.0`) when it has none.
+pub fn component_key(region: Region, index: usize, config: &ComponentConfig) -> String {
+    config
+        .id()
+        .map_or_else(|| format!("{}.{index}", region.name()), str::to_owned)
+}
+
+/// The widget tree of the bar: the components of the left, center and right regions.
+///
+/// Every component is registered under its [`component_key`] so modules can reach it
+/// through [`SimbarWidgets::update`] and change its content between frames.
 pub struct SimbarWidgets {
-    left: OnceLock<RwLock<LeftWidgets>>,
-    center: OnceLock<RwLock<CenterWidgets>>,
-    right: OnceLock<RwLock<RightWidgets>>,
+    left: RwLock<LeftWidgets>,
+    center: RwLock<CenterWidgets>,
+    right: RwLock<RightWidgets>,
+    direction: Direction,
+    ids: HashMap<String, (Region, usize)>,
+}
+
+impl SimbarWidgets {
+    /// Builds the components of every region with the theme fonts, laid out along the edge the
+    /// bar is anchored to and `simbar.height` logical pixels thick.
+    pub fn from_config(config: &Config) -> Self {
+        let direction = config.simbar.anchor.direction();
+        let thickness = config.simbar.height;
+        let fonts = fonts::resolve(&config.theme.font);
+        let mut ids = HashMap::new();
+
+        let mut build = |region: Region, configs: &[ComponentConfig]| {
+            configs
+                .iter()
+                .enumerate()
+                .map(|(index, config)| {
+                    let key = component_key(region, index, config);
+
+                    if ids.insert(key.clone(), (region, index)).is_some() {
+                        eprintln!("Widget id `{key}` is used more than once, the last one wins");
+                    }
+
+                    build_component(config, fonts)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let left = build(Region::Left, &config.widgets.left);
+        let center = build(Region::Center, &config.widgets.center);
+        let right = build(Region::Right, &config.widgets.right);
+
+        Self {
+            left: RwLock::new(LeftWidgets {
+                components: left,
+                thickness,
+                direction,
+            }),
+            center: RwLock::new(CenterWidgets {
+                components: center,
+                thickness,
+                direction,
+            }),
+            right: RwLock::new(RightWidgets {
+                components: right,
+                thickness,
+                direction,
+            }),
+            direction,
+            ids,
+        }
+    }
+
+    /// Renders the three regions into one canvas, see [`composite`] for the overlap rule.
+    pub fn render(&self, area: DrawSize, scale: f32) -> Rendered {
+        let left = self.left.read().expect("Left widgets lock poisoned");
+        let center = self.center.read().expect("Center widgets lock poisoned");
+        let right = self.right.read().expect("Right widgets lock poisoned");
+
+        composite(area, scale, self.direction, &[&*left, &*right, &*center])
+    }
+
+    /// Runs `f` on the component registered under `id`, if it exists and is a `T`.
+    ///
+    /// Returns whether `f` was called.
+    pub fn update<T: Component>(&self, id: &str, f: impl FnOnce(&mut T)) -> bool {
+        let Some(&(region, index)) = self.ids.get(id) else {
+            return false;
+        };
+
+        self.with_components_mut(region, |components| {
+            let component: &mut dyn Any = components[index].as_mut();
+            component.downcast_mut::<T>().map(f).is_some()
+        })
+    }
+
+    /// Whether any component changed since [`SimbarWidgets::clear_dirty`] was last called.
+    pub fn is_dirty(&self) -> bool {
+        [Region::Left, Region::Center, Region::Right]
+            .into_iter()
+            .any(|region| {
+                self.with_components(region, |components| {
+                    components.iter().any(|component| component.is_dirty())
+                })
+            })
+    }
+
+    /// Marks every component as drawn.
+    pub fn clear_dirty(&self) {
+        for region in [Region::Left, Region::Center, Region::Right] {
+            self.with_components_mut(region, |components| {
+                components
+                    .iter_mut()
+                    .for_each(|component| component.clear_dirty())
+            });
+        }
+    }
+
+    fn with_components<R>(
+        &self,
+        region: Region,
+        f: impl FnOnce(&[Box<dyn Component + Send + Sync>]) -> R,
+    ) -> R {
+        match region {
+            Region::Left => f(&self
+                .left
+                .read()
+                .expect("Left widgets lock poisoned")
+                .components),
+            Region::Center => f(&self
+                .center
+                .read()
+                .expect("Center widgets lock poisoned")
+                .components),
+            Region::Right => f(&self
+                .right
+                .read()
+                .expect("Right widgets lock poisoned")
+                .components),
+        }
+    }
+
+    fn with_components_mut<R>(
+        &self,
+        region: Region,
+        f: impl FnOnce(&mut Vec<Box<dyn Component + Send + Sync>>) -> R,
+    ) -> R {
+        match region {
+            Region::Left => f(&mut self
+                .left
+                .write()
+                .expect("Left widgets lock poisoned")
+                .components),
+            Region::Center => f(&mut self
+                .center
+                .write()
+                .expect("Center widgets lock poisoned")
+                .components),
+            Region::Right => f(&mut self
+                .right
+                .write()
+                .expect("Right widgets lock poisoned")
+                .components),
+        }
+    }
+}
+
+/// The widget trees of every monitor, which modules update together.
+pub struct WidgetTrees<'a>(Vec<&'a SimbarWidgets>);
+
+impl<'a> FromIterator<&'a SimbarWidgets> for WidgetTrees<'a> {
+    fn from_iter<I: IntoIterator<Item = &'a SimbarWidgets>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl WidgetTrees<'_> {
+    /// Runs `f` on the component registered under `id` in every tree, see
+    /// [`SimbarWidgets::update`].
+    ///
+    /// Returns whether `f` was called at least once.
+    pub fn update<T: Component>(&self, id: &str, mut f: impl FnMut(&mut T)) -> bool {
+        let mut updated = false;
+
+        for widgets in self.0.iter() {
+            updated |= widgets.update(id, &mut f);
+        }
+
+        updated
+    }
+}
+
+/// Composites the regions of the bar into a single canvas of `area` size.
+///
+/// Regions are painted in order. Each one replaces everything below it across the span of
+/// the `direction` axis it actually draws on, so when regions overlap the later one wins
+/// cleanly instead of having its glyphs interleaved with the earlier one's. `SimBar` paints
+/// left, then right, then center, keeping the center region always fully visible.
+pub fn composite(
+    area: DrawSize,
+    scale: f32,
+    direction: Direction,
+    regions: &[&dyn Widgets],
+) -> Rendered {
+    let width = area.width as usize;
+    let mut canvas = vec![None; width * area.height as usize];
+    let mut placements = Vec::new();
+
+    for region in regions {
+        let rendered = region.render(area, scale);
+        placements.extend(rendered.placements);
+
+        let mut drawn = rendered
+            .pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, pixel)| pixel.is_some())
+            .map(|(i, _)| match direction {
+                Direction::Row => i % width,
+                Direction::Column => i / width,
+            });
+
+        let Some(first) = drawn.next() else {
+            continue;
+        };
+        let (start, end) = drawn.fold((first, first), |(start, end), i| (start.min(i), end.max(i)));
+
+        match direction {
+            Direction::Row => {
+                for (canvas_row, row) in canvas
+                    .chunks_exact_mut(width)
+                    .zip(rendered.pixels.chunks_exact(width))
+                {
+                    canvas_row[start..=end].copy_from_slice(&row[start..=end]);
+                }
+            }
+            Direction::Column => {
+                let rows = start * width..(end + 1) * width;
+                canvas[rows.clone()].copy_from_slice(&rendered.pixels[rows]);
+            }
+        }
+    }
+
+    Rendered {
+        pixels: canvas,
+        placements,
+    }
+}
+
+/// Lays `components` out as a single line along `direction` and copies their pixels into a
+/// canvas of `area` size.
+///
+/// The line is placed along the bar by `justify` and is at most `thickness` logical pixels
+/// thick, centered across the bar; each component is centered across the line. Content
+/// overflowing the canvas is clipped according to [`Align`].
+fn render_line(
+    components: &[Box<dyn Component + Send + Sync>],
+    area: DrawSize,
+    scale: f32,
+    direction: Direction,
+    thickness: u32,
+    justify: Align,
+) -> Rendered {
+    let mut buffer = vec![None; (area.width * area.height) as usize];
+
+    let rendered: Vec<_> = components
+        .iter()
+        .map(|component| component.render(scale, direction))
+        .collect();
+
+    let line = Node::container(
+        direction,
+        rendered.iter().map(|(bbox, _)| Node::leaf(*bbox)).collect(),
+    )
+    .with_justify(justify)
+    .with_align_items(Align::Center);
+
+    let (length, cross) = direction.split((area.width, area.height));
+    let thickness = ((thickness as f32 * scale).round() as u32).min(cross);
+    let (width, height) = direction.join(length, thickness);
+    let (x, y) = direction.join_position(0, i64::from((cross - thickness) / 2));
+
+    let rects = line.layout(Rect {
+        x,
+        y,
+        width,
+        height,
+    });
+
+    for ((bbox, pixels), rect) in rendered.iter().zip(rects.iter()) {
+        blit(&mut buffer, area, *rect, *bbox, pixels);
+    }
+
+    Rendered {
+        pixels: buffer,
+        placements: components
+            .iter()
+            .zip(rects)
+            .map(|(component, rect)| Placement {
+                rect,
+                dirty: component.is_dirty(),
+            })
+            .collect(),
+    }
+}
+
+/// Copies the `bbox`-sized `pixels` of a component into `rect` of the canvas, clipping to
+/// both the rectangle and the canvas.
+fn blit(
+    buffer: &mut [Option<ArgbColor>],
+    area: DrawSize,
+    rect: Rect,
+    bbox: BoundingBox,
+    pixels: &[Option<ArgbColor>],
+) {
+    let width = bbox.width.min(rect.width) as usize;
+    let height = bbox.height.min(rect.height) as usize;
+
+    for y in 0..height {
+        let canvas_y = rect.y as i64 + y as i64;
+        if canvas_y < 0 || canvas_y >= i64::from(area.height) {
+            continue;
+        }
+
+        for x in 0..width {
+            let canvas_x = rect.x as i64 + x as i64;
+            if canvas_x < 0 || canvas_x >= i64::from(area.width) {
+                continue;
+            }
+
+            let src_idx = y * bbox.width as usize + x;
+            let dest_idx = canvas_y as usize * area.width as usize + canvas_x as usize;
+
+            if src_idx < pixels.len() && dest_idx < buffer.len() {
+                buffer[dest_idx] = pixels[src_idx];
+            }
+        }
+    }
 }